@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{graph::Graph, grb};
+
+/// Names of the columns read from the node and edge tables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Columns {
+    pub id: String,
+    pub labels: String,
+    pub src: String,
+    pub dst: String,
+    pub typ: String,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns {
+            id: "id".to_string(),
+            labels: "labels".to_string(),
+            src: "src".to_string(),
+            dst: "dst".to_string(),
+            typ: "type".to_string(),
+        }
+    }
+}
+
+/// Parse a comma separated list of overrides of the default columns, like
+/// `id=node_id,type=rel`.
+impl FromStr for Columns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut columns = Columns::default();
+        for mapping in s.split(',') {
+            let (key, name) = mapping
+                .split_once('=')
+                .ok_or(format!("invalid column mapping: {}", mapping))?;
+            let column = match key.trim() {
+                "id" => &mut columns.id,
+                "labels" => &mut columns.labels,
+                "src" => &mut columns.src,
+                "dst" => &mut columns.dst,
+                "type" => &mut columns.typ,
+                key => return Err(format!("unknown column: {}", key)),
+            };
+            *column = name.trim().to_string();
+        }
+        Ok(columns)
+    }
+}
+
+/// Split a CSV line into its fields. Quoted fields may contain commas and
+/// doubled quotes.
+fn fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unterminated quoted field".to_string()),
+                }
+            }
+            if !matches!(chars.peek(), Some(',') | None) {
+                return Err("unexpected text after quoted field".to_string());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != ',') {
+                field.push(c);
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+/// Rows of a CSV table with a header, reporting errors with the file and the
+/// line.
+struct Table {
+    path: PathBuf,
+    header: Vec<String>,
+    lines: std::iter::Enumerate<io::Lines<BufReader<std::fs::File>>>,
+}
+
+impl Table {
+    fn open(path: PathBuf) -> io::Result<Table> {
+        let mut lines = BufReader::new(std::fs::File::open(&path)?)
+            .lines()
+            .enumerate();
+        let header = match lines.next() {
+            Some((_, line)) => fields(&line?),
+            None => Err("missing header".to_string()),
+        };
+        let mut table = Table {
+            path,
+            header: vec![],
+            lines,
+        };
+        table.header = header.map_err(|msg| table.error(0, msg))?;
+        Ok(table)
+    }
+
+    fn error(&self, num: usize, msg: String) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", self.path.display(), num + 1, msg),
+        )
+    }
+
+    /// Position of the column, if it is required or present.
+    fn column(&self, name: &str, required: bool) -> io::Result<Option<usize>> {
+        match self.header.iter().position(|column| column == name) {
+            None if required => Err(self.error(0, format!("missing column: {}", name))),
+            pos => Ok(pos),
+        }
+    }
+
+    /// Get the line number and the fields of the next non-empty row.
+    fn row(&mut self) -> io::Result<Option<(usize, Vec<String>)>> {
+        for (num, line) in self.lines.by_ref() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row = fields(&line).map_err(|msg| self.error(num, msg))?;
+            if row.len() != self.header.len() {
+                let msg = format!("expected {} fields, found {}", self.header.len(), row.len());
+                return Err(self.error(num, msg));
+            }
+            return Ok(Some((num, row)));
+        }
+        Ok(None)
+    }
+}
+
+/// Load a property graph from `nodes.csv` and `edges.csv` in the directory.
+///
+/// Every edge type becomes a label. Every node label becomes a diagonal
+/// matrix named `:<label>` which keeps only vertices with the label, e.g.
+/// `?x <knows>/<:Person> ?y`. Node labels are separated by `;`.
+pub fn load_csv(path: &Path, columns: &Columns) -> io::Result<Graph> {
+    let mut verts: HashMap<String, usize> = HashMap::new();
+    let mut edges: HashMap<String, Vec<(grb::Index, grb::Index)>> = HashMap::new();
+
+    let mut nodes = Table::open(path.join("nodes.csv"))?;
+    let id = nodes.column(&columns.id, true)?.unwrap();
+    let labels = nodes.column(&columns.labels, false)?;
+    while let Some((num, row)) = nodes.row()? {
+        let idx = verts.len();
+        if verts.insert(row[id].clone(), idx).is_some() {
+            return Err(nodes.error(num, format!("duplicate node: {}", row[id])));
+        }
+        let node_labels = labels.map_or("", |labels| &row[labels]);
+        for label in node_labels.split(';').filter(|label| !label.is_empty()) {
+            let pair = (idx as grb::Index, idx as grb::Index);
+            edges.entry(format!(":{}", label)).or_default().push(pair);
+        }
+    }
+
+    let mut rels = Table::open(path.join("edges.csv"))?;
+    let src = rels.column(&columns.src, true)?.unwrap();
+    let dst = rels.column(&columns.dst, true)?.unwrap();
+    let typ = rels.column(&columns.typ, true)?.unwrap();
+    while let Some((num, row)) = rels.row()? {
+        let vertex = |name: &str| match verts.get(name) {
+            Some(&idx) => Ok(idx as grb::Index),
+            None => Err(rels.error(num, format!("no such node: {}", name))),
+        };
+        let edge = (vertex(&row[src])?, vertex(&row[dst])?);
+        edges.entry(row[typ].clone()).or_default().push(edge);
+    }
+
+    Graph::new(verts, edges).map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(
+            fields("1,Person;Student,"),
+            Ok(vec!["1".into(), "Person;Student".into(), "".into()])
+        );
+        assert_eq!(
+            fields(r#""a, ""b""",c"#),
+            Ok(vec![r#"a, "b""#.into(), "c".into()])
+        );
+        assert_eq!(
+            fields(r#""a"#),
+            Err("unterminated quoted field".to_string())
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let columns: Columns = "id=node_id, type=rel".parse().unwrap();
+        assert_eq!(columns.id, "node_id");
+        assert_eq!(columns.typ, "rel");
+        assert_eq!(columns.src, "src");
+        assert_eq!(
+            "weight=w".parse::<Columns>(),
+            Err("unknown column: weight".to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_csv() {
+        crate::eval::init_test();
+        let dir = std::env::temp_dir().join(format!("rpq-{}-csv", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("nodes.csv"),
+            "node_id,labels\n1,Person;Student\n2,Person\n\"3, x\",\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("edges.csv"),
+            "src,dst,type\n1,2,knows\n2,\"3, x\",knows\n",
+        )
+        .unwrap();
+        let columns: Columns = "id=node_id".parse().unwrap();
+        let graph = load_csv(&dir, &columns).unwrap();
+        assert_eq!(graph.nverts(), 3);
+        assert_eq!(graph.labels(), vec![":Person", ":Student", "knows"]);
+        assert_eq!(graph.edges(":Person"), vec![("1", "1"), ("2", "2")]);
+        assert_eq!(graph.edges(":Student"), vec![("1", "1")]);
+        assert_eq!(graph.edges("knows"), vec![("1", "2"), ("2", "3, x")]);
+
+        std::fs::write(dir.join("edges.csv"), "src,dst,type\n1,4,knows\n").unwrap();
+        assert!(load_csv(&dir, &columns)
+            .err()
+            .unwrap()
+            .to_string()
+            .ends_with("no such node: 4"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use egg::{EGraph, Id, Language, RecExpr};
+
+use crate::plan::Plan;
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn node_label(node: &Plan) -> String {
+    match node {
+        Plan::Label(meta) => format!("{}\\nnvals: {}", escape(&meta.name), meta.nvals),
+        Plan::Vertex(meta) => escape(&meta.to_string()),
+        op => escape(&op.to_string()),
+    }
+}
+
+fn node_shape(node: &Plan) -> &'static str {
+    match node {
+        Plan::Label(_) | Plan::Vertex(_) => "box",
+        _ => "ellipse",
+    }
+}
+
+/// Render the plan as a graph. Shared subplans are drawn once.
+pub fn plan_dot(expr: &RecExpr<Plan>) -> String {
+    let mut res = String::from("digraph plan {\n");
+    for (id, node) in expr.items() {
+        writeln!(
+            res,
+            "  n{} [label=\"{}\", shape={}];",
+            id,
+            node_label(node),
+            node_shape(node)
+        )
+        .unwrap();
+        for (i, child) in node.children().iter().enumerate() {
+            writeln!(res, "  n{} -> n{} [label=\"{}\"];", id, child, i).unwrap();
+        }
+    }
+    res.push_str("}\n");
+    res
+}
+
+/// Find e-nodes the plan was extracted from as `(class, node index)` pairs.
+///
+/// Nodes missing from the e-graph, like the transposition added at the root
+/// of a plan extracted for the `from-dest` hint, are skipped with the nodes
+/// above them.
+fn plan_enodes(egraph: &EGraph<Plan, ()>, expr: &RecExpr<Plan>) -> Vec<(Id, usize)> {
+    let mut classes: Vec<Option<Id>> = vec![];
+    let mut res = vec![];
+    for node in expr.as_ref() {
+        let known = |child: &Id| classes[usize::from(*child)].is_some();
+        let found = if node.children().iter().all(known) {
+            let mut node = node
+                .clone()
+                .map_children(|child| classes[usize::from(child)].unwrap());
+            egraph.lookup(&mut node).and_then(|class| {
+                let idx = egraph[class]
+                    .nodes
+                    .iter()
+                    .position(|enode| *enode == node)?;
+                Some((class, idx))
+            })
+        } else {
+            None
+        };
+        classes.push(found.map(|(class, _)| class));
+        res.extend(found);
+    }
+    res
+}
+
+/// Render the e-graph with every e-class as a cluster and e-nodes of the
+/// extracted plan highlighted.
+pub fn egraph_dot(egraph: &EGraph<Plan, ()>, plan: &RecExpr<Plan>) -> String {
+    let chosen = plan_enodes(egraph, plan);
+    let mut res = String::from("digraph egraph {\n  compound=true;\n  clusterrank=local;\n");
+    for class in egraph.classes() {
+        writeln!(
+            res,
+            "  subgraph cluster_{} {{\n    style=dotted;\n    label=\"{}\";",
+            class.id, class.id
+        )
+        .unwrap();
+        for (i, node) in class.nodes.iter().enumerate() {
+            let style = if chosen.contains(&(class.id, i)) {
+                ", style=filled, fillcolor=gold"
+            } else {
+                ""
+            };
+            writeln!(
+                res,
+                "    c{}_{} [label=\"{}\", shape={}{}];",
+                class.id,
+                i,
+                node_label(node),
+                node_shape(node),
+                style
+            )
+            .unwrap();
+        }
+        res.push_str("  }\n");
+    }
+    for class in egraph.classes() {
+        for (i, node) in class.nodes.iter().enumerate() {
+            for (arg, child) in node.children().iter().enumerate() {
+                let child = egraph.find(*child);
+                writeln!(
+                    res,
+                    "  c{}_{} -> c{}_0 [lhead=cluster_{}, label=\"{}\"];",
+                    class.id, i, child, child, arg
+                )
+                .unwrap();
+            }
+        }
+    }
+    res.push_str("}\n");
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+
+    #[test]
+    fn test_plan_dot() {
+        let expr = "(vxm {v} (* 2))".parse().unwrap();
+        expect![[r#"
+            digraph plan {
+              n0 [label="{v}", shape=box];
+              n1 [label="-\nnvals: 2", shape=box];
+              n2 [label="*", shape=ellipse];
+              n2 -> n1 [label="0"];
+              n3 [label="vxm", shape=ellipse];
+              n3 -> n0 [label="0"];
+              n3 -> n2 [label="1"];
+            }
+        "#]]
+        .assert_eq(plan_dot(&expr).as_str());
+    }
+
+    #[test]
+    fn test_egraph_dot_chosen() {
+        let expr: RecExpr<Plan> = "(/ {v} 2)".parse().unwrap();
+        let mut egraph = EGraph::<Plan, ()>::default();
+        egraph.add_expr(&expr);
+        egraph.rebuild();
+        let dot = egraph_dot(&egraph, &expr);
+        assert_eq!(dot.matches("fillcolor=gold").count(), 3);
+    }
+
+    #[test]
+    fn test_egraph_dot_from_dest() {
+        let expr: RecExpr<Plan> = "(* 2)".parse().unwrap();
+        let mut egraph = EGraph::<Plan, ()>::default();
+        egraph.add_expr(&expr);
+        egraph.rebuild();
+        // The transposition of a from-dest plan is not in the e-graph.
+        let plan = "(t (* 2))".parse().unwrap();
+        let dot = egraph_dot(&egraph, &plan);
+        assert_eq!(dot.matches("fillcolor=gold").count(), 2);
+    }
+}
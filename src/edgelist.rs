@@ -0,0 +1,69 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{graph::Graph, grb};
+
+/// Load a graph from a text file with a `<src> <label> <dest>` edge on every
+/// line, separated by whitespace. Empty lines and lines starting with `#` are
+/// skipped.
+pub fn load_edge_list(path: &Path) -> io::Result<Graph> {
+    let file = BufReader::new(std::fs::File::open(path)?);
+    let mut verts: HashMap<String, usize> = HashMap::new();
+    let mut edges: HashMap<String, Vec<(grb::Index, grb::Index)>> = HashMap::new();
+    let mut vertex = |name: &str| {
+        let next = verts.len();
+        *verts.entry(name.to_string()).or_insert(next) as grb::Index
+    };
+    for (num, line) in file.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [src, label, dest] => {
+                let edge = (vertex(src), vertex(dest));
+                edges.entry(label.to_string()).or_default().push(edge);
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}:{}: expected <src> <label> <dest>",
+                        path.display(),
+                        num + 1
+                    ),
+                ))
+            }
+        }
+    }
+    Graph::new(verts, edges).map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_edge_list() {
+        crate::eval::init_test();
+        let path = std::env::temp_dir().join(format!("rpq-{}-edges.txt", std::process::id()));
+        std::fs::write(&path, "# comment\na knows b\n\n  b knows c\nc likes a\n").unwrap();
+        let graph = load_edge_list(&path).unwrap();
+        assert_eq!(graph.nverts(), 3);
+        assert_eq!(graph.labels(), vec!["knows", "likes"]);
+        assert_eq!(graph.edges("knows"), vec![("a", "b"), ("b", "c")]);
+        assert_eq!(graph.edges("likes"), vec![("c", "a")]);
+
+        std::fs::write(&path, "a knows b\na knows\n").unwrap();
+        assert!(load_edge_list(&path)
+            .err()
+            .unwrap()
+            .to_string()
+            .ends_with(":2: expected <src> <label> <dest>"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
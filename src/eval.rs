@@ -1,8 +1,15 @@
+use std::fmt;
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use egg::{Id, Language, RecExpr};
 
 use crate::graph::Graph;
 use crate::grb;
-use crate::plan::Plan;
+use crate::plan::{estimate_nodes, push_transposes, EstimateCostFn, Plan};
+use crate::profile::{NodeProfile, Profile};
 
 #[repr(C)]
 #[derive(Clone)]
@@ -44,14 +51,214 @@ extern "C" {
 
 #[link(name = "lagraph")]
 extern "C" {
+    pub fn LAGraph_SetNumThreads(
+        nthreads_outer: libc::c_int,
+        nthreads_inner: libc::c_int,
+        msg: *mut libc::c_char,
+    ) -> libc::c_int;
+    pub fn LAGraph_GetNumThreads(
+        nthreads_outer: *mut libc::c_int,
+        nthreads_inner: *mut libc::c_int,
+        msg: *mut libc::c_char,
+    ) -> libc::c_int;
     pub fn LAGraph_MMRead(
         mat: *mut grb::Matrix,
         f: *mut libc::FILE,
         msg: *mut libc::c_char,
     ) -> libc::c_int;
+    pub fn LAGraph_Matrix_IsEqual(
+        res: *mut bool,
+        lhs: *mut libc::c_void,
+        rhs: *mut libc::c_void,
+        msg: *mut libc::c_char,
+    ) -> libc::c_int;
+}
+
+/// Set the number of threads used by GraphBLAS operations of LAGraph and of
+/// plans evaluated node by node outside of a thread pool.
+pub fn set_threads(nthreads: i32) -> Result<(), String> {
+    grb::check(
+        unsafe { LAGraph_SetNumThreads(1, nthreads, null_mut()) },
+        "LAGraph_SetNumThreads",
+    )
+}
+
+/// Number of threads used by GraphBLAS operations.
+pub fn threads() -> Result<i32, String> {
+    let (mut outer, mut inner) = (0, 0);
+    grb::check(
+        unsafe { LAGraph_GetNumThreads(&mut outer, &mut inner, null_mut()) },
+        "LAGraph_GetNumThreads",
+    )?;
+    Ok(inner)
+}
+
+/// Reason an evaluation stopped without an answer.
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    TimedOut,
+    Cancelled,
+    /// An intermediate result needs (or is estimated to need) `bytes`, more
+    /// than the `budget`.
+    OverBudget {
+        bytes: u64,
+        budget: u64,
+    },
+    Failed(String),
+}
+
+/// Approximate size of a boolean matrix entry: its column index, its value
+/// and a share of the row pointers.
+pub const BYTES_PER_NVAL: u64 = 16;
+
+impl From<String> for EvalError {
+    fn from(msg: String) -> Self {
+        EvalError::Failed(msg)
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::TimedOut => write!(f, "timed out"),
+            EvalError::Cancelled => write!(f, "cancelled"),
+            EvalError::OverBudget { bytes, budget } => write!(
+                f,
+                "an intermediate result needs {} MiB, over the memory budget of {} MiB",
+                bytes >> 20,
+                budget >> 20
+            ),
+            EvalError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Deadline, memory budget and cancellation token checked between evaluation
+/// steps. Clones share the token, so an evaluation can be cancelled from
+/// another thread.
+#[derive(Clone, Default)]
+pub struct Cancel {
+    deadline: Option<Instant>,
+    budget: Option<u64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancel {
+    /// Build a token sharing the cancellation flag of this one which also
+    /// expires after `timeout`.
+    pub fn with_timeout(&self, timeout: Duration) -> Cancel {
+        Cancel {
+            deadline: Some(Instant::now() + timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Build a token sharing the cancellation flag of this one which also
+    /// limits intermediate results to `budget` bytes.
+    pub fn with_budget(&self, budget: u64) -> Cancel {
+        Cancel {
+            budget: Some(budget),
+            ..self.clone()
+        }
+    }
+
+    pub fn has_budget(&self) -> bool {
+        self.budget.is_some()
+    }
+
+    /// Check a result of `nvals` entries fits the memory budget.
+    pub fn check_nvals(&self, nvals: u64) -> Result<(), EvalError> {
+        let bytes = nvals.saturating_mul(BYTES_PER_NVAL);
+        match self.budget {
+            Some(budget) if bytes > budget => Err(EvalError::OverBudget { bytes, budget }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn check(&self) -> Result<(), EvalError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(EvalError::Cancelled)
+        } else if self
+            .deadline
+            .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            Err(EvalError::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Part of the result selected by a constant vertex: a single row which is
+/// expanded as a vector.
+struct Frontier {
+    row: grb::Index,
+    vec: grb::Vector,
+}
+
+fn vertex_index(graph: &Graph, name: &str) -> Result<grb::Index, String> {
+    graph
+        .verts
+        .get(name)
+        .map(|&vert_idx| vert_idx as grb::Index)
+        .ok_or(format!("no such vertex: {}", name))
+}
+
+/// Build the diagonal matrix selecting a single vertex.
+pub fn selector(graph: &Graph, name: &str) -> Result<grb::Matrix, String> {
+    let vert_idx = vertex_index(graph, name)?;
+    let mut mat = grb::Matrix::null();
+    unsafe {
+        LAGraph_RPQMatrix_label(
+            &mut mat as *mut grb::Matrix,
+            vert_idx as usize,
+            graph.dim() as usize,
+            graph.dim() as usize,
+        );
+    }
+    Ok(mat)
+}
+
+/// Mark nodes evaluated as matrices. Plans below `vxm` are expanded as
+/// vectors instead.
+fn matrix_nodes(expr: &RecExpr<Plan>) -> Vec<bool> {
+    let mut used = vec![false; expr.as_ref().len()];
+    *used.last_mut().unwrap() = true;
+    for (id, plan) in expr.as_ref().iter().enumerate().rev() {
+        if used[id] && !matches!(plan, Plan::Vxm(_)) {
+            plan.for_each(|child| used[usize::from(child)] = true);
+        }
+    }
+    used
 }
 
 pub fn eval(graph: &Graph, expr: egg::RecExpr<Plan>) -> Result<usize, String> {
+    let expr = push_transposes(&expr);
+    let root = Id::from(expr.as_ref().len() - 1);
+    if let Plan::Vxm(_) = expr[root] {
+        let cancel = Cancel::default();
+        return Evaluator::new(graph, &expr, &cancel)
+            .root()
+            .map_err(|err| err.to_string());
+    }
+    eval_rpq(graph, &expr, |_| Ok(()))
+}
+
+/// Evaluate the transpose-normalized plan with a single `LAGraph_RPQMatrix`
+/// call. The evaluated nodes, indexed like the plan, are passed to `inspect`
+/// before they are destroyed.
+fn eval_rpq(
+    graph: &Graph,
+    expr: &RecExpr<Plan>,
+    inspect: impl FnOnce(&[RpqMatrixPlan]) -> Result<(), String>,
+) -> Result<usize, String> {
+    let used = matrix_nodes(expr);
+    // Label matrices stay alive until the call is done, even if evicted.
+    let mut labels = vec![];
     let mut plans: Vec<RpqMatrixPlan> = vec![
         RpqMatrixPlan {
             op: RpqMatrixOp::Label,
@@ -62,7 +269,10 @@ pub fn eval(graph: &Graph, expr: egg::RecExpr<Plan>) -> Result<usize, String> {
         };
         expr.len()
     ];
-    expr.items().for_each(|(id, plan)| {
+    for (id, plan) in expr.items() {
+        if !used[usize::from(id)] {
+            continue;
+        }
         let eval_plan = match plan {
             &Plan::Seq([lhs, rhs]) => RpqMatrixPlan {
                 op: RpqMatrixOp::Concat,
@@ -100,41 +310,554 @@ pub fn eval(graph: &Graph, expr: egg::RecExpr<Plan>) -> Result<usize, String> {
                 mat: grb::Matrix::null(),
             },
             Plan::Label(meta) => {
-                let mut mat: grb::Matrix = grb::Matrix(std::ptr::null_mut());
-                let mat = graph
-                    .mats
-                    .get(&meta.name)
-                    .or({
-                        graph.verts.get(&meta.name).map(|vert_idx| {
-                            unsafe {
-                                LAGraph_RPQMatrix_label(
-                                    &mut mat as *mut grb::Matrix,
-                                    *vert_idx - 1,
-                                    graph.verts.len(),
-                                    graph.verts.len(),
-                                );
-                            }
-                            &mat
-                        })
-                    })
-                    .unwrap()
-                    .clone();
+                labels.push(graph.matrix(&meta.name)?);
+                RpqMatrixPlan {
+                    op: RpqMatrixOp::Label,
+                    lhs: null_mut(),
+                    rhs: null_mut(),
+                    res_mat: grb::Matrix::null(),
+                    mat: labels.last().unwrap().0.clone(),
+                }
+            }
+            &Plan::Transpose([label]) => {
+                match &expr[label] {
+                    Plan::Label(meta) => labels.push(graph.transposed(&meta.name)?),
+                    _ => return Err("only labels can be transposed".to_string()),
+                };
+                RpqMatrixPlan {
+                    op: RpqMatrixOp::Label,
+                    lhs: null_mut(),
+                    rhs: null_mut(),
+                    res_mat: grb::Matrix::null(),
+                    mat: labels.last().unwrap().0.clone(),
+                }
+            }
+            &Plan::Vxm(_) => {
+                let cancel = Cancel::default();
+                let Frontier { row, vec } = Evaluator::new(graph, expr, &cancel)
+                    .frontier(id)
+                    .map_err(|err| err.to_string())?;
                 RpqMatrixPlan {
                     op: RpqMatrixOp::Label,
                     lhs: null_mut(),
                     rhs: null_mut(),
                     res_mat: grb::Matrix::null(),
-                    mat,
+                    mat: vec.to_row(row, graph.dim())?,
                 }
             }
+            Plan::Vertex(meta) => RpqMatrixPlan {
+                op: RpqMatrixOp::Label,
+                lhs: null_mut(),
+                rhs: null_mut(),
+                res_mat: grb::Matrix::null(),
+                mat: selector(graph, &meta.name)?,
+            },
         };
         plans[std::convert::Into::<usize>::into(id)] = eval_plan;
-    });
-    let plan = plans.iter_mut().last().unwrap();
+    }
+    let plan = plans.iter_mut().last().unwrap() as *mut RpqMatrixPlan;
     let mut ans: usize = 0;
-    unsafe {
-        LAGraph_RPQMatrix(&mut ans, plan as *mut RpqMatrixPlan, null_mut());
+    let inspected = unsafe {
+        LAGraph_RPQMatrix(&mut ans, plan, null_mut());
+        let inspected = inspect(&plans);
         LAGraph_DestroyRpqMatrixPlan(plan);
+        inspected
+    };
+    inspected.map(|_| ans)
+}
+
+/// Intermediate result of a step-by-step evaluation.
+enum Value {
+    Shared(Arc<grb::OwnedMatrix>),
+    Owned(grb::OwnedMatrix),
+}
+
+impl Value {
+    fn mat(&self) -> &grb::Matrix {
+        match self {
+            Value::Shared(mat) => mat,
+            Value::Owned(mat) => mat,
+        }
+    }
+
+    fn into_owned(self) -> Result<grb::OwnedMatrix, String> {
+        match self {
+            Value::Shared(mat) => mat.dup(),
+            Value::Owned(mat) => Ok(mat),
+        }
+    }
+}
+
+/// Step-by-step evaluation of a plan with GraphBLAS operations, checking
+/// `cancel` between steps and optionally profiling every node.
+struct Evaluator<'a> {
+    graph: &'a Graph,
+    expr: &'a RecExpr<Plan>,
+    cancel: &'a Cancel,
+    profile: Option<Mutex<Vec<NodeProfile>>>,
+    /// Evaluate independent operands concurrently on the current thread pool.
+    parallel: bool,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(graph: &'a Graph, expr: &'a RecExpr<Plan>, cancel: &'a Cancel) -> Self {
+        Evaluator {
+            graph,
+            expr,
+            cancel,
+            profile: None,
+            parallel: false,
+        }
+    }
+
+    fn profiled(graph: &'a Graph, expr: &'a RecExpr<Plan>, cancel: &'a Cancel) -> Self {
+        let nodes = vec![NodeProfile::default(); expr.as_ref().len()];
+        Evaluator {
+            profile: Some(Mutex::new(nodes)),
+            ..Evaluator::new(graph, expr, cancel)
+        }
+    }
+
+    /// Account a result of the node computed since `start`.
+    fn record(
+        &self,
+        id: Id,
+        start: Instant,
+        nvals: impl FnOnce() -> Result<grb::Index, String>,
+    ) -> Result<(), String> {
+        if let Some(profile) = &self.profile {
+            // Querying nvals waits for pending GraphBLAS work.
+            let nvals = nvals()?;
+            let node = &mut profile.lock().unwrap()[usize::from(id)];
+            node.calls += 1;
+            node.time += start.elapsed();
+            node.nvals = nvals;
+        }
+        Ok(())
+    }
+
+    fn record_iteration(&self, id: Id) {
+        if let Some(profile) = &self.profile {
+            profile.lock().unwrap()[usize::from(id)].iterations += 1;
+        }
+    }
+
+    /// Run both operations, concurrently if the evaluation is parallel.
+    fn join<A: Send, B: Send>(
+        &self,
+        lhs: impl FnOnce() -> A + Send,
+        rhs: impl FnOnce() -> B + Send,
+    ) -> (A, B) {
+        if self.parallel {
+            rayon::join(lhs, rhs)
+        } else {
+            (lhs(), rhs())
+        }
+    }
+
+    fn frontier(&self, id: Id) -> Result<Frontier, EvalError> {
+        let start = Instant::now();
+        let frontier = match &self.expr[id] {
+            Plan::Vertex(meta) => {
+                let row = vertex_index(self.graph, &meta.name)?;
+                let mut vec = grb::Vector::new(self.graph.dim())?;
+                vec.set(row)?;
+                Frontier { row, vec }
+            }
+            &Plan::Vxm([lhs, rhs]) => {
+                let Frontier { row, vec } = self.frontier(lhs)?;
+                let vec = self.expand(vec, rhs)?;
+                Frontier { row, vec }
+            }
+            // Other plans of a frontier class, e.g. `(/ {v} <a>)`, select a
+            // single row too. They are evaluated as matrices, recording
+            // themselves, and their row is taken.
+            _ => {
+                let res = self.node(id)?;
+                let mut rows: Vec<grb::Index> =
+                    res.mat().pairs()?.into_iter().map(|(row, _)| row).collect();
+                rows.sort_unstable();
+                rows.dedup();
+                let row = match rows[..] {
+                    [] => 0,
+                    [row] => row,
+                    _ => return Err("vxm expects a single row frontier".to_string().into()),
+                };
+                let mut unit = grb::Vector::new(self.graph.dim())?;
+                unit.set(row)?;
+                let vec = unit.vxm(res.mat())?;
+                return Ok(Frontier { row, vec });
+            }
+        };
+        self.record(id, start, || frontier.vec.nvals())?;
+        Ok(frontier)
+    }
+
+    /// Compute `vec * plan` following the plan edges from the frontier
+    /// vertices.
+    fn expand(&self, vec: grb::Vector, id: Id) -> Result<grb::Vector, EvalError> {
+        self.cancel.check()?;
+        let start = Instant::now();
+        let res = match &self.expr[id] {
+            Plan::Label(meta) => vec.vxm(&*self.graph.matrix(&meta.name)?)?,
+            &Plan::Transpose([label]) => match &self.expr[label] {
+                Plan::Label(meta) => vec.vxm(&*self.graph.transposed(&meta.name)?)?,
+                _ => return Err("only labels can be transposed".to_string().into()),
+            },
+            Plan::Vertex(meta) => {
+                let vert_idx = vertex_index(self.graph, &meta.name)?;
+                let mut res = grb::Vector::new(vec.size()?)?;
+                if vec.contains(vert_idx)? {
+                    res.set(vert_idx)?;
+                }
+                res
+            }
+            &Plan::Seq([lhs, rhs]) | &Plan::Vxm([lhs, rhs]) => {
+                let vec = self.expand(vec, lhs)?;
+                self.expand(vec, rhs)?
+            }
+            &Plan::Alt([lhs, rhs]) => {
+                let lhs_vec = vec.dup()?;
+                let (res, rhs_res) =
+                    self.join(|| self.expand(lhs_vec, lhs), || self.expand(vec, rhs));
+                let mut res = res?;
+                res.union(&rhs_res?)?;
+                res
+            }
+            &Plan::Star([lhs]) => self.closure(vec, id, lhs)?,
+            &Plan::LStar([lhs, rhs]) => {
+                let vec = self.closure(vec, id, lhs)?;
+                self.expand(vec, rhs)?
+            }
+            &Plan::RStar([lhs, rhs]) => {
+                let vec = self.expand(vec, lhs)?;
+                self.closure(vec, id, rhs)?
+            }
+        };
+        self.record(id, start, || res.nvals())?;
+        Ok(res)
+    }
+
+    /// Compute `vec * step*` for the closure node `id`, expanding only newly
+    /// reached vertices.
+    fn closure(&self, vec: grb::Vector, id: Id, step: Id) -> Result<grb::Vector, EvalError> {
+        let mut visited = vec.dup()?;
+        let mut frontier = vec;
+        while frontier.nvals()? != 0 {
+            self.record_iteration(id);
+            frontier = self.expand(frontier, step)?.without(&visited)?;
+            visited.union(&frontier)?;
+        }
+        Ok(visited)
+    }
+
+    /// Compute `start * step*` (or `step* * start` depending on `step`) for
+    /// the closure node `id`, joining only newly found pairs on every
+    /// iteration.
+    fn fixpoint(
+        &self,
+        id: Id,
+        start: Value,
+        step: impl Fn(&grb::Matrix) -> Result<grb::OwnedMatrix, String>,
+    ) -> Result<Value, EvalError> {
+        let mut res = start.into_owned()?;
+        let mut delta = res.dup()?;
+        while delta.nvals()? != 0 {
+            self.cancel.check()?;
+            self.record_iteration(id);
+            delta = step(&delta)?.without(&res)?;
+            res.union(&delta)?;
+            if self.cancel.has_budget() {
+                self.cancel.check_nvals(res.nvals()? + delta.nvals()?)?;
+            }
+        }
+        Ok(Value::Owned(res))
+    }
+
+    /// Evaluate the independent operands of a binary node.
+    fn operands(&self, lhs: Id, rhs: Id) -> Result<(Value, Value), EvalError> {
+        let (lhs, rhs) = self.join(|| self.node(lhs), || self.node(rhs));
+        Ok((lhs?, rhs?))
+    }
+
+    fn node(&self, id: Id) -> Result<Value, EvalError> {
+        self.cancel.check()?;
+        let start = Instant::now();
+        let res = match &self.expr[id] {
+            Plan::Label(meta) => Value::Shared(self.graph.matrix(&meta.name)?),
+            &Plan::Transpose([label]) => match &self.expr[label] {
+                Plan::Label(meta) => Value::Shared(self.graph.transposed(&meta.name)?),
+                _ => return Err("only labels can be transposed".to_string().into()),
+            },
+            Plan::Vertex(meta) => Value::Owned(grb::OwnedMatrix(selector(self.graph, &meta.name)?)),
+            &Plan::Vxm(_) => {
+                let Frontier { row, vec } = self.frontier(id)?;
+                Value::Owned(grb::OwnedMatrix(vec.to_row(row, self.graph.dim())?))
+            }
+            &Plan::Seq([lhs, rhs]) => {
+                let (lhs, rhs) = self.operands(lhs, rhs)?;
+                Value::Owned(lhs.mat().mxm(rhs.mat())?)
+            }
+            &Plan::Alt([lhs, rhs]) => {
+                let (lhs, rhs) = self.operands(lhs, rhs)?;
+                let mut res = lhs.into_owned()?;
+                res.union(rhs.mat())?;
+                Value::Owned(res)
+            }
+            &Plan::Star([lhs]) => {
+                let step = self.node(lhs)?;
+                let start = grb::OwnedMatrix::identity(self.graph.dim())?;
+                self.fixpoint(id, Value::Owned(start), |delta| delta.mxm(step.mat()))?
+            }
+            &Plan::LStar([lhs, rhs]) => {
+                let (step, start) = self.operands(lhs, rhs)?;
+                self.fixpoint(id, start, |delta| step.mat().mxm(delta))?
+            }
+            &Plan::RStar([lhs, rhs]) => {
+                let (start, step) = self.operands(lhs, rhs)?;
+                self.fixpoint(id, start, |delta| delta.mxm(step.mat()))?
+            }
+        };
+        if let (Value::Owned(mat), true) = (&res, self.cancel.has_budget()) {
+            self.cancel.check_nvals(mat.nvals()?)?;
+        }
+        // A vxm node records itself as a frontier.
+        if !matches!(self.expr[id], Plan::Vxm(_)) {
+            self.record(id, start, || res.mat().nvals())?;
+        }
+        Ok(res)
+    }
+
+    /// Evaluate the root of the plan returning the number of answers.
+    fn root(&self) -> Result<usize, EvalError> {
+        let root = Id::from(self.expr.as_ref().len() - 1);
+        let nvals = match self.expr[root] {
+            Plan::Vxm(_) => self.frontier(root)?.vec.nvals()?,
+            _ => self.node(root)?.mat().nvals()?,
+        };
+        Ok(nvals as usize)
+    }
+}
+
+/// Check the estimated sizes of intermediate matrices fit the memory budget
+/// before evaluating anything.
+fn check_estimates(graph: &Graph, expr: &RecExpr<Plan>, cancel: &Cancel) -> Result<(), EvalError> {
+    let used = matrix_nodes(expr);
+    let cost_fn = EstimateCostFn {
+        nverts: graph.nverts(),
+        workers: 1,
+        stats: graph.stats(),
+    };
+    let estimates = estimate_nodes(expr, cost_fn);
+    for (id, plan) in expr.as_ref().iter().enumerate() {
+        if used[id] && !matches!(plan, Plan::Label(_) | Plan::Transpose(_)) {
+            cancel.check_nvals(estimates[id].nvals as u64)?;
+        }
+    }
+    Ok(())
+}
+
+/// Evaluate the plan node by node with GraphBLAS operations instead of a
+/// single `LAGraph_RPQMatrix` call, so it can be stopped through `cancel`
+/// between nodes and closure iterations. Plans whose estimated intermediate
+/// results exceed the memory budget are rejected without being evaluated.
+pub fn eval_cancellable(
+    graph: &Graph,
+    expr: egg::RecExpr<Plan>,
+    cancel: &Cancel,
+) -> Result<usize, EvalError> {
+    let expr = push_transposes(&expr);
+    check_estimates(graph, &expr, cancel)?;
+    Evaluator::new(graph, &expr, cancel).root()
+}
+
+/// Evaluate the plan like [`eval_cancellable`] on the thread pool, computing
+/// independent operands concurrently.
+pub fn eval_parallel(
+    graph: &Graph,
+    expr: egg::RecExpr<Plan>,
+    cancel: &Cancel,
+    pool: &rayon::ThreadPool,
+) -> Result<usize, EvalError> {
+    let expr = push_transposes(&expr);
+    check_estimates(graph, &expr, cancel)?;
+    let evaluator = Evaluator {
+        parallel: true,
+        ..Evaluator::new(graph, &expr, cancel)
+    };
+    pool.install(|| evaluator.root())
+}
+
+/// Build a pool of `workers` threads for [`eval_parallel`], splitting the
+/// GraphBLAS threads evenly between them.
+pub fn thread_pool(workers: usize) -> Result<rayon::ThreadPool, String> {
+    let nthreads = (grb::global_threads()? / workers.max(1) as i32).max(1);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .start_handler(move |_| {
+            if let Err(msg) = grb::engage_threads(nthreads) {
+                eprintln!("unable to limit GraphBLAS threads: {}", msg);
+            }
+        })
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// Evaluate the plan like [`eval_cancellable`] recording the time, result
+/// size and fixpoint iterations of every node.
+pub fn eval_profiled(
+    graph: &Graph,
+    expr: egg::RecExpr<Plan>,
+    cancel: &Cancel,
+) -> Result<(usize, Profile), EvalError> {
+    let expr = push_transposes(&expr);
+    check_estimates(graph, &expr, cancel)?;
+    let evaluator = Evaluator::profiled(graph, &expr, cancel);
+    let answer = evaluator.root()?;
+    let nodes = evaluator.profile.unwrap().into_inner().unwrap();
+    let profile = Profile {
+        expr,
+        nodes,
+        timed: true,
+    };
+    Ok((answer, profile))
+}
+
+/// Evaluate the plan like [`eval`] recording the size of the result of every
+/// node computed by `LAGraph_RPQMatrix`. Its time and iterations are not
+/// known, only the time of the whole evaluation is recorded at the root.
+///
+/// Plans with `vxm` at the root are not evaluated by LAGraph, they are
+/// profiled like with [`eval_profiled`].
+pub fn eval_profiled_rpq(
+    graph: &Graph,
+    expr: egg::RecExpr<Plan>,
+) -> Result<(usize, Profile), EvalError> {
+    let expr = push_transposes(&expr);
+    let root = expr.as_ref().len() - 1;
+    if let Plan::Vxm(_) = expr.as_ref()[root] {
+        return eval_profiled(graph, expr, &Cancel::default());
+    }
+    let mut nodes = vec![NodeProfile::default(); expr.as_ref().len()];
+    let used = matrix_nodes(&expr);
+    let start = Instant::now();
+    let answer = eval_rpq(graph, &expr, |plans| {
+        for (id, plan) in plans.iter().enumerate().filter(|(id, _)| used[*id]) {
+            // Labels, vertices and frontiers are inputs of LAGraph.
+            let res = match plan.op {
+                RpqMatrixOp::Label => &plan.mat,
+                _ => &plan.res_mat,
+            };
+            nodes[id].calls = 1;
+            nodes[id].nvals = res.nvals()?;
+        }
+        Ok(())
+    })?;
+    nodes[root].time = start.elapsed();
+    let profile = Profile {
+        expr,
+        nodes,
+        timed: false,
+    };
+    Ok((answer, profile))
+}
+
+/// Initialize LAGraph once before a test calls GraphBLAS.
+#[cfg(test)]
+pub fn init_test() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| assert_eq!(unsafe { LAGraph_Init(null_mut()) }, 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Graph of 5 vertices with `a` edges 0→1, 0→2, 3→1 and `b` edges 1→3,
+    /// 2→4, 2→3.
+    fn ab_graph() -> Graph {
+        init_test();
+        let verts = (0..5).map(|idx| (idx.to_string(), idx)).collect();
+        let edges = std::collections::HashMap::from([
+            ("a".to_string(), vec![(0, 1), (0, 2), (3, 1)]),
+            ("b".to_string(), vec![(1, 3), (2, 4), (2, 3)]),
+        ]);
+        Graph::new(verts, edges).unwrap()
+    }
+
+    #[test]
+    fn test_eval_vxm_plans() {
+        use crate::plan::{enumerate_plans, make_rules};
+
+        let graph = ab_graph();
+        for (query, answers) in [
+            ("<0> <a>/<b> ?x", 2),
+            ("<0> (<a>/<b>)* ?x", 3),
+            ("?x <a>/<b> <3>", 2),
+            ("<0> <a>/<b> <4>", 1),
+        ] {
+            let expr = graph.run(query.parse().unwrap()).unwrap();
+            let runner = egg::Runner::default().with_expr(&expr).run(&make_rules());
+            let plans = enumerate_plans(&runner.egraph, runner.roots[0], 1000);
+            assert!(plans.iter().any(|plan| plan
+                .as_ref()
+                .iter()
+                .any(|node| matches!(node, Plan::Vxm(_)))));
+            for plan in plans {
+                let cancel = Cancel::default();
+                assert_eq!(
+                    eval(&graph, plan.clone()),
+                    Ok(answers),
+                    "{} {}",
+                    query,
+                    plan
+                );
+                assert_eq!(
+                    eval_cancellable(&graph, plan.clone(), &cancel),
+                    Ok(answers),
+                    "{} {}",
+                    query,
+                    plan
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_profile_rpq() {
+        let graph = ab_graph();
+        let plan = graph.resolve(&crate::plan::parse_plan("(| (/ <a> <b>) <b>)").unwrap());
+        let (answer, profile) = eval_profiled_rpq(&graph, plan.unwrap()).unwrap();
+        assert_eq!(answer, 6);
+        assert!(!profile.timed);
+        let nvals: Vec<u64> = profile.nodes.iter().map(|node| node.nvals).collect();
+        assert_eq!(nvals, vec![3, 3, 3, 3, 6]);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let token = Cancel::default();
+        assert_eq!(token.check(), Ok(()));
+        let expired = token.with_timeout(Duration::ZERO);
+        assert_eq!(expired.check(), Err(EvalError::TimedOut));
+        let cancel = token.with_timeout(Duration::from_secs(3600));
+        assert_eq!(cancel.check(), Ok(()));
+        token.cancel();
+        assert_eq!(cancel.check(), Err(EvalError::Cancelled));
+    }
+
+    #[test]
+    fn test_budget() {
+        let cancel = Cancel::default();
+        assert_eq!(cancel.check_nvals(u64::MAX), Ok(()));
+        let cancel = cancel.with_budget(1 << 20);
+        assert_eq!(cancel.check_nvals((1 << 20) / BYTES_PER_NVAL), Ok(()));
+        assert_eq!(
+            cancel.check_nvals(1 << 20),
+            Err(EvalError::OverBudget {
+                bytes: BYTES_PER_NVAL << 20,
+                budget: 1 << 20
+            })
+        );
     }
-    Ok(ans)
 }
@@ -1,26 +1,338 @@
 use std::{
     collections::HashMap,
     ffi::CString,
-    io,
+    io::{self, BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use egg::{Id, RecExpr};
 
 use crate::{
+    csv::{load_csv, Columns},
+    edgelist::load_edge_list,
     eval::LAGraph_MMRead,
     grb,
-    plan::{LabelMeta, Plan},
+    ntriples::load_ntriples,
+    plan::{LabelMeta, Plan, VertexMeta},
     query::{Pattern, Query, Vertex},
+    snapshot::load_snapshot,
+    stats::Stats,
 };
 
+/// Matrix of a label, read from its file on first use when it has one.
+struct Label {
+    /// The matrix, shared with the evaluations using it so evicting it only
+    /// frees it once they are done.
+    mat: Option<Arc<grb::OwnedMatrix>>,
+    file: Option<PathBuf>,
+    /// Whether the rows and columns of the vertices have to be extracted
+    /// from the matrix read from the file.
+    extract: bool,
+    /// Tick of the last use, to evict the least recently used matrices.
+    used: u64,
+}
+
 pub struct Graph {
     nvals: HashMap<String, usize>,
-    pub mats: HashMap<String, grb::Matrix>,
+    labels: Mutex<HashMap<String, Label>>,
+    tick: AtomicU64,
+    /// Matrix index of every vertex, from 0 to the number of vertices.
     pub verts: HashMap<String, usize>,
+    /// Name of the vertex of every matrix index.
+    names: Vec<String>,
+    /// Rows of the vertices in the matrix files, by matrix index.
+    rows: Vec<grb::Index>,
+    /// Id in `vertices.txt` of the first row of the matrix files.
+    base: usize,
+    transposed: Mutex<HashMap<String, Arc<grb::OwnedMatrix>>>,
+    /// Statistics of the labels for the planner, if they are known.
+    stats: Option<Arc<Stats>>,
 }
 
 impl Graph {
+    /// Build a graph from the matrix indices of its vertices and the edges of
+    /// every label as pairs of indices.
+    pub fn new(
+        verts: HashMap<String, usize>,
+        edges: HashMap<String, Vec<(grb::Index, grb::Index)>>,
+    ) -> Result<Graph, String> {
+        let mats = edges
+            .into_iter()
+            .map(|(label, pairs)| {
+                Ok((
+                    label,
+                    grb::Matrix::build(verts.len() as grb::Index, &pairs)?,
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        Graph::from_mats(verts, mats)
+    }
+
+    /// Build a graph from the matrix indices of its vertices and the
+    /// matrices of its labels.
+    pub fn from_mats(
+        verts: HashMap<String, usize>,
+        mats: HashMap<String, grb::Matrix>,
+    ) -> Result<Graph, String> {
+        let mut nvals = HashMap::new();
+        let mut labels = HashMap::new();
+        for (name, mat) in mats {
+            mat.wait()?;
+            nvals.insert(name.clone(), mat.nvals()? as usize);
+            let label = Label {
+                mat: Some(Arc::new(grb::OwnedMatrix(mat))),
+                file: None,
+                extract: false,
+                used: 0,
+            };
+            labels.insert(name, label);
+        }
+        Ok(Graph {
+            nvals,
+            labels: Mutex::new(labels),
+            tick: AtomicU64::default(),
+            names: vertex_names(&verts)?,
+            rows: (0..verts.len() as grb::Index).collect(),
+            base: 1,
+            verts,
+            transposed: Mutex::default(),
+            stats: None,
+        })
+    }
+
+    /// Get the names of all labels in order.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.nvals.keys().cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    /// Get the matrix of the label, reading it on first use. The file is read
+    /// without holding the lock, so other labels can be used meanwhile.
+    pub fn matrix(&self, name: &str) -> Result<Arc<grb::OwnedMatrix>, String> {
+        let (file, extract) = {
+            let mut labels = self.labels.lock().unwrap();
+            let label = labels
+                .get_mut(name)
+                .ok_or(format!("no such label: {}", name))?;
+            label.used = self.tick.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(mat) = &label.mat {
+                return Ok(mat.clone());
+            }
+            (label.file.clone().unwrap(), label.extract)
+        };
+        let mat = grb::OwnedMatrix(read_matrix(&file)?);
+        let mat = match extract {
+            true => grb::OwnedMatrix(mat.extract(&self.rows)?),
+            false => mat,
+        };
+        let mut labels = self.labels.lock().unwrap();
+        let label = labels.get_mut(name).unwrap();
+        // Another thread may have read the file meanwhile.
+        Ok(label.mat.get_or_insert_with(|| Arc::new(mat)).clone())
+    }
+
+    /// Drop the least recently used matrices read from files, keeping at most
+    /// `keep` of them. Matrices still used by an evaluation are freed when it
+    /// is done with them.
+    pub fn evict(&self, keep: usize) {
+        let mut transposed = self.transposed.lock().unwrap();
+        let mut labels = self.labels.lock().unwrap();
+        let mut loaded: Vec<(&String, &mut Label)> = labels
+            .iter_mut()
+            .filter(|(_, label)| label.mat.is_some() && label.file.is_some())
+            .collect();
+        loaded.sort_by_key(|(_, label)| std::cmp::Reverse(label.used));
+        for (name, label) in loaded.into_iter().skip(keep) {
+            label.mat = None;
+            transposed.remove(name);
+        }
+    }
+
+    pub fn nverts(&self) -> usize {
+        self.verts.len()
+    }
+
+    /// Get the name of the vertex with the matrix index.
+    pub fn vertex_name(&self, idx: grb::Index) -> &str {
+        &self.names[idx as usize]
+    }
+
+    /// Get the edges of the label as sorted pairs of vertex names.
+    #[cfg(test)]
+    pub fn edges(&self, label: &str) -> Vec<(&str, &str)> {
+        let pairs = self.matrix(label).unwrap().pairs().unwrap();
+        let mut edges: Vec<_> = pairs
+            .into_iter()
+            .map(|(src, dest)| (self.vertex_name(src), self.vertex_name(dest)))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// Dimension of the label matrices.
+    pub fn dim(&self) -> grb::Index {
+        self.names.len() as grb::Index
+    }
+
+    /// Get the statistics of the labels, if they are known.
+    pub fn stats(&self) -> Option<Arc<Stats>> {
+        self.stats.clone()
+    }
+
+    pub fn set_stats(&mut self, stats: Option<Arc<Stats>>) {
+        self.stats = stats;
+    }
+
+    /// Get the transposed matrix of the label, transposing it on first use.
+    pub fn transposed(&self, name: &str) -> Result<Arc<grb::OwnedMatrix>, String> {
+        if let Some(mat) = self.transposed.lock().unwrap().get(name) {
+            return Ok(mat.clone());
+        }
+        let mat = grb::OwnedMatrix(self.matrix(name)?.transpose()?);
+        mat.wait()?;
+        let mut transposed = self.transposed.lock().unwrap();
+        Ok(transposed
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(mat))
+            .clone())
+    }
+
+    /// Read the matrices of all labels and keep them in memory only, so they
+    /// can be changed.
+    fn materialize(&mut self) -> Result<(), String> {
+        for name in self.labels() {
+            self.matrix(&name)?;
+            self.labels.get_mut().unwrap().get_mut(&name).unwrap().file = None;
+        }
+        Ok(())
+    }
+
+    /// Get the index of the vertex, adding it without edges when missing.
+    /// Adding a vertex reads the matrices of all labels to grow them.
+    pub fn add_vertex(&mut self, name: &str) -> Result<grb::Index, String> {
+        if let Some(&idx) = self.verts.get(name) {
+            return Ok(idx as grb::Index);
+        }
+        self.materialize()?;
+        let idx = self.names.len();
+        let dim = idx as grb::Index + 1;
+        for label in self.labels.get_mut().unwrap().values() {
+            label.mat.as_ref().unwrap().resize(dim, dim)?;
+        }
+        self.transposed.get_mut().unwrap().clear();
+        self.verts.insert(name.to_string(), idx);
+        self.names.push(name.to_string());
+        // New vertices follow the rows of the others in the matrix files.
+        self.rows.push(self.rows.last().map_or(0, |row| row + 1));
+        Ok(idx as grb::Index)
+    }
+
+    /// Get the matrix of the label to change it, adding the label when
+    /// missing.
+    fn label_mut(&mut self, name: &str) -> Result<Arc<grb::OwnedMatrix>, String> {
+        if !self.nvals.contains_key(name) {
+            let label = Label {
+                mat: Some(Arc::new(grb::OwnedMatrix(grb::Matrix::build(
+                    self.dim(),
+                    &[],
+                )?))),
+                file: None,
+                extract: false,
+                used: 0,
+            };
+            self.nvals.insert(name.to_string(), 0);
+            self.labels
+                .get_mut()
+                .unwrap()
+                .insert(name.to_string(), label);
+        }
+        let mat = self.matrix(name)?;
+        self.labels.get_mut().unwrap().get_mut(name).unwrap().file = None;
+        self.transposed.get_mut().unwrap().remove(name);
+        Ok(mat)
+    }
+
+    /// Add an edge, adding its vertices and label when missing. Returns
+    /// whether the edge is new. Changing the graph drops the statistics of
+    /// its labels.
+    pub fn insert_edge(&mut self, src: &str, label: &str, dest: &str) -> Result<bool, String> {
+        let src = self.add_vertex(src)?;
+        let dest = self.add_vertex(dest)?;
+        let mat = self.label_mut(label)?;
+        if mat.contains(src, dest)? {
+            return Ok(false);
+        }
+        mat.set(src, dest)?;
+        mat.wait()?;
+        self.nvals.insert(label.to_string(), mat.nvals()? as usize);
+        self.stats = None;
+        Ok(true)
+    }
+
+    /// Remove an edge, keeping its vertices and label. Returns whether the
+    /// edge existed.
+    pub fn delete_edge(&mut self, src: &str, label: &str, dest: &str) -> Result<bool, String> {
+        let (src, dest) = match (self.verts.get(src), self.verts.get(dest)) {
+            (Some(&src), Some(&dest)) if self.nvals.contains_key(label) => {
+                (src as grb::Index, dest as grb::Index)
+            }
+            _ => return Ok(false),
+        };
+        let mat = self.label_mut(label)?;
+        if !mat.contains(src, dest)? {
+            return Ok(false);
+        }
+        mat.remove(src, dest)?;
+        mat.wait()?;
+        self.nvals.insert(label.to_string(), mat.nvals()? as usize);
+        self.stats = None;
+        Ok(true)
+    }
+
+    /// Write the graph into the dataset directory it may have been loaded
+    /// from, reading all label matrices first.
+    ///
+    /// The graph is written into a new directory next to it, which then
+    /// replaces it, so an interrupted write leaves the old directory intact.
+    /// The other entries of the old directory, subdirectories and links
+    /// included, are then moved into it, except the statistics and the old
+    /// matrix files.
+    pub fn write_back(&mut self, path: &Path) -> io::Result<()> {
+        self.materialize()
+            .map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))?;
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no directory name"))?
+            .to_string_lossy();
+        let new = path.with_file_name(format!(".{}.new", name));
+        let old = path.with_file_name(format!(".{}.old", name));
+        if new.exists() {
+            std::fs::remove_dir_all(&new)?;
+        }
+        save_dir(self, &new)?;
+        let mut stale = vec!["stats.tsv".into()];
+        if path.join("edges.txt").exists() {
+            for (_, num) in read_ids(&path.join("edges.txt"))? {
+                stale.push(format!("{}.txt", num).into());
+            }
+        }
+        std::fs::rename(path, &old)?;
+        std::fs::rename(&new, path)?;
+        for entry in std::fs::read_dir(&old)? {
+            let name = entry?.file_name();
+            let target = path.join(&name);
+            if !stale.contains(&name) && target.symlink_metadata().is_err() {
+                std::fs::rename(old.join(&name), target)?;
+            }
+        }
+        std::fs::remove_dir_all(&old)
+    }
+
     fn plan_aux(&self, expr: &mut RecExpr<Plan>, pattern: Pattern) -> Result<Id, String> {
         match pattern {
             Pattern::Uri(uri) => Ok(expr.add(Plan::Label(LabelMeta {
@@ -49,10 +361,31 @@ impl Graph {
                 let aux = expr.add(Plan::Star([lhs]));
                 Ok(expr.add(Plan::Seq([lhs, aux])))
             }
-            Pattern::Opt(_lhs) => {
-                todo!("opt (?) queries are not supported yet")
-            }
+            Pattern::Opt(_) => Err("optional (?) patterns are not supported yet".to_string()),
+        }
+    }
+
+    /// Check that labels and vertices of the plan exist in the graph and fill
+    /// in the label sizes.
+    pub fn resolve(&self, expr: &RecExpr<Plan>) -> Result<RecExpr<Plan>, String> {
+        let mut res = RecExpr::default();
+        for node in expr.as_ref() {
+            let node = match node {
+                Plan::Label(meta) => Plan::Label(LabelMeta {
+                    name: meta.name.clone(),
+                    nvals: *self
+                        .nvals
+                        .get(&meta.name)
+                        .ok_or(format!("no such label: {}", meta.name))?,
+                }),
+                Plan::Vertex(meta) if !self.verts.contains_key(&meta.name) => {
+                    return Err(format!("no such vertex: {}", meta.name))
+                }
+                node => node.clone(),
+            };
+            res.add(node);
         }
+        Ok(res)
     }
 
     pub fn run(&self, query: Query) -> Result<RecExpr<Plan>, String> {
@@ -68,7 +401,7 @@ impl Graph {
                 pattern,
                 dest: Vertex::Any,
             } => {
-                let lhs = expr.add(Plan::Label(LabelMeta { name, nvals: 1 }));
+                let lhs = expr.add(Plan::Vertex(VertexMeta { name }));
                 let rhs = self.plan_aux(&mut expr, pattern)?;
                 expr.add(Plan::Seq([lhs, rhs]))
             }
@@ -78,99 +411,617 @@ impl Graph {
                 dest: Vertex::Con(name),
             } => {
                 let lhs = self.plan_aux(&mut expr, pattern)?;
-                let rhs = expr.add(Plan::Label(LabelMeta { name, nvals: 1 }));
+                let rhs = expr.add(Plan::Vertex(VertexMeta { name }));
                 expr.add(Plan::Seq([lhs, rhs]))
             }
+            // Selecting the source row and the destination column leaves at
+            // most the single pair.
             Query {
-                src: Vertex::Con(_v1),
-                pattern: _,
-                dest: Vertex::Con(_v2),
+                src: Vertex::Con(src),
+                pattern,
+                dest: Vertex::Con(dest),
             } => {
-                todo!("con to con queries are not supported yet")
+                let lhs = expr.add(Plan::Vertex(VertexMeta { name: src }));
+                let pattern = self.plan_aux(&mut expr, pattern)?;
+                let rhs = expr.add(Plan::Vertex(VertexMeta { name: dest }));
+                let rhs = expr.add(Plan::Seq([pattern, rhs]));
+                expr.add(Plan::Seq([lhs, rhs]))
             }
         };
         Ok(expr)
     }
 }
 
-pub fn load_dir(path: &Path) -> io::Result<Graph> {
-    let dirs = std::fs::read_dir(path)?;
+/// Load a graph from a dataset directory (see [`load_dir`]), a property graph
+/// directory with `nodes.csv` and `edges.csv`, an N-Triples file ending with
+/// `.nt`, a snapshot ending with `.snap` or an edge list file.
+pub fn load(path: &Path, columns: &Columns) -> io::Result<Graph> {
+    if path.extension().map_or(false, |ext| ext == "snap") {
+        load_snapshot(path)
+    } else if path.is_dir() && path.join("nodes.csv").exists() {
+        load_csv(path, columns)
+    } else if path.is_dir() {
+        load_dir(path)
+    } else if path.extension().map_or(false, |ext| ext == "nt") {
+        load_ntriples(path)
+    } else {
+        load_edge_list(path)
+    }
+}
 
-    let edges_file = path.join("edges.txt");
-    let edges: HashMap<usize, String> = std::fs::read_to_string(edges_file)?
-        .lines()
-        .filter_map(|line| {
-            let mut splits = line.split_whitespace();
-            let edge = splits.next()?;
-            let edge = edge[1..edge.len() - 1].to_string();
-            let num = splits.next()?.parse::<usize>().ok()?;
-            Some((num, edge))
-        })
-        .collect();
+/// Write the graph into the directory in the layout read by [`load_dir`]:
+/// `edges.txt` numbering the labels, `vertices.txt` numbering the vertices
+/// and a MatrixMarket `<number>.txt` file for every label. Vertices loaded
+/// from a dataset directory keep their ids.
+pub fn save_dir(graph: &Graph, path: &Path) -> io::Result<()> {
+    let other = |msg| io::Error::new(io::ErrorKind::Other, msg);
+    std::fs::create_dir_all(path)?;
 
-    let verts_file = path.join("vertices.txt");
-    let verts: HashMap<String, usize> = std::fs::read_to_string(verts_file)?
-        .lines()
-        .filter_map(|line| {
-            let mut splits = line.split_whitespace();
-            let vert = splits.next()?;
-            let vert = vert[1..vert.len() - 1].to_string();
-            let num = splits.next()?.parse::<usize>().ok()?;
-            Some((vert, num))
-        })
-        .collect();
+    let mut file = BufWriter::new(std::fs::File::create(path.join("vertices.txt"))?);
+    if graph.base == 0 {
+        writeln!(file, "% base 0")?;
+    }
+    for (vert, &row) in graph.names.iter().zip(&graph.rows) {
+        writeln!(file, "{} {}", write_name(vert), row as usize + graph.base)?;
+    }
+    file.flush()?;
+    let dim = graph.rows.last().map_or(0, |row| row + 1);
 
-    let mat_files: Vec<(String, PathBuf)> = dirs
-        .flatten()
-        .map(|entry| entry.path())
-        .filter_map(|entry| Some(entry.file_stem()?.to_str()?.to_string()))
-        .filter_map(|entry| entry.parse::<usize>().ok())
-        .filter_map(|entry| {
-            Some((
-                edges.get(&entry)?.clone(),
-                path.join(format!("{}.txt", entry)),
-            ))
-        })
-        .collect();
+    let labels = graph.labels();
+    let mut file = BufWriter::new(std::fs::File::create(path.join("edges.txt"))?);
+    for (num, label) in labels.iter().enumerate() {
+        writeln!(file, "{} {}", write_name(label), num + 1)?;
+    }
+    file.flush()?;
 
-    let nvals: HashMap<String, usize> = mat_files
-        .iter()
-        .filter_map(|(edge, file)| {
-            // TODO: read only first 3 lines :/.
-            let edge_nvals = std::fs::read_to_string(file)
-                .ok()?
-                .lines()
-                .nth(2)?
+    for (num, label) in labels.iter().enumerate() {
+        let mat = graph.matrix(label).map_err(other)?;
+        let mut pairs: Vec<_> = mat
+            .pairs()
+            .map_err(other)?
+            .into_iter()
+            .map(|(row, col)| (graph.rows[row as usize], graph.rows[col as usize]))
+            .collect();
+        pairs.sort();
+        let mut file = BufWriter::new(std::fs::File::create(
+            path.join(format!("{}.txt", num + 1)),
+        )?);
+        writeln!(file, "%%MatrixMarket matrix coordinate pattern general")?;
+        writeln!(file, "%%GraphBLAS type bool")?;
+        writeln!(file, "{} {} {}", dim, dim, pairs.len())?;
+        for (row, col) in pairs {
+            writeln!(file, "{} {}", row + 1, col + 1)?;
+        }
+        file.flush()?;
+    }
+    Ok(())
+}
+
+fn invalid_data(file: &Path, line: usize, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}:{}: {}", file.display(), line, msg),
+    )
+}
+
+/// Size of a MatrixMarket matrix: rows, columns and entries.
+type Header = (usize, usize, usize);
+
+/// Read the banner and the size line of a MatrixMarket file, the first line
+/// which is not a comment.
+fn read_header(file: &Path) -> io::Result<Header> {
+    let lines = BufReader::new(std::fs::File::open(file)?).lines();
+    for (num, line) in lines.enumerate() {
+        let line = line?;
+        if num == 0 && !line.starts_with("%%MatrixMarket matrix coordinate") {
+            return Err(invalid_data(
+                file,
+                1,
+                "not a MatrixMarket coordinate matrix",
+            ));
+        }
+        if !line.starts_with('%') {
+            let size: Vec<usize> = line
                 .split_whitespace()
-                .nth(2)?
-                .parse::<usize>()
-                .ok()?;
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid_data(file, num + 1, "invalid size line"))?;
+            return match size[..] {
+                [nrows, ncols, nvals] => Ok((nrows, ncols, nvals)),
+                _ => Err(invalid_data(file, num + 1, "invalid size line")),
+            };
+        }
+    }
+    Err(invalid_data(file, 1, "missing size line"))
+}
 
-            Some((edge.clone(), edge_nvals))
-        })
-        .collect();
+/// Split a name off the start of the text. The name is either in angle
+/// brackets or in double quotes with `\"` and `\\` escapes, so it may contain
+/// whitespace.
+pub fn parse_name(text: &str) -> Result<(String, &str), String> {
+    if let Some(rest) = text.strip_prefix('<') {
+        let end = rest.find('>').ok_or("unterminated <name>")?;
+        Ok((rest[..end].to_string(), &rest[end + 1..]))
+    } else if let Some(rest) = text.strip_prefix('"') {
+        let mut name = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => name.push(c),
+                    _ => return Err("invalid escape in quoted name".to_string()),
+                },
+                Some((end, '"')) => break end,
+                Some((_, c)) => name.push(c),
+                None => return Err("unterminated quoted name".to_string()),
+            }
+        };
+        Ok((name, &rest[end + 1..]))
+    } else {
+        Err("expected <name> or \"name\"".to_string())
+    }
+}
+
+/// Parse a `<name> <id>` line of `edges.txt` or `vertices.txt` (see
+/// [`parse_name`]).
+fn parse_id_line(line: &str) -> Result<(String, usize), String> {
+    let (name, rest) = parse_name(line)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return Err("expected whitespace after the name".to_string());
+    }
+    let id = rest
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid id: {}", rest.trim()))?;
+    Ok((name, id))
+}
+
+/// Invert the map of vertex indices, checking they are distinct and dense.
+fn vertex_names(verts: &HashMap<String, usize>) -> Result<Vec<String>, String> {
+    let mut names = vec![None; verts.len()];
+    for (name, &idx) in verts {
+        match names.get_mut(idx) {
+            Some(slot @ None) => *slot = Some(name.clone()),
+            _ => return Err(format!("invalid index {} of vertex {}", idx, name)),
+        }
+    }
+    Ok(names.into_iter().map(Option::unwrap).collect())
+}
+
+/// Write a name for [`parse_name`], quoting it when it contains `>`.
+pub fn write_name(name: &str) -> String {
+    if name.contains('>') {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("<{}>", name)
+    }
+}
+
+/// Read the names and ids of `edges.txt` or `vertices.txt`, rejecting
+/// duplicate names and ids. Empty lines and `%` comments are skipped.
+fn read_ids(file: &Path) -> io::Result<Vec<(String, usize)>> {
+    let mut names = HashMap::new();
+    let mut ids = HashMap::new();
+    let mut res = vec![];
+    for (num, line) in BufReader::new(std::fs::File::open(file)?)
+        .lines()
+        .enumerate()
+    {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('%') {
+            continue;
+        }
+        let (name, id) =
+            parse_id_line(line.trim()).map_err(|msg| invalid_data(file, num + 1, msg))?;
+        if let Some(prev) = ids.insert(id, num + 1) {
+            let msg = format!("duplicate id {} (first on line {})", id, prev);
+            return Err(invalid_data(file, num + 1, msg));
+        }
+        if let Some(prev) = names.insert(name.clone(), num + 1) {
+            let msg = format!("duplicate name {} (first on line {})", name, prev);
+            return Err(invalid_data(file, num + 1, msg));
+        }
+        res.push((name, id));
+    }
+    Ok(res)
+}
+
+/// Read the id of the first row and column of the matrix files from a
+/// `% base <0 or 1>` line starting `vertices.txt`. Ids are 1-based without it.
+fn read_base(file: &Path) -> io::Result<usize> {
+    let first = match BufReader::new(std::fs::File::open(file)?).lines().next() {
+        Some(line) => line?,
+        None => return Ok(1),
+    };
+    match first.split_whitespace().collect::<Vec<_>>()[..] {
+        ["%", "base", "0"] => Ok(0),
+        ["%", "base", "1"] => Ok(1),
+        ["%", "base", ..] => Err(invalid_data(file, 1, "expected % base 0 or % base 1")),
+        _ => Ok(1),
+    }
+}
+
+/// Count the entries of a MatrixMarket file in the given rows and columns,
+/// which are sorted.
+fn count_entries(file: &Path, rows: &[grb::Index]) -> io::Result<usize> {
+    let kept = |idx: &str| {
+        let row = idx.parse::<grb::Index>().ok()?.checked_sub(1)?;
+        Some(rows.binary_search(&row).is_ok())
+    };
+    let mut size_line = true;
+    let mut count = 0;
+    for (num, line) in BufReader::new(std::fs::File::open(file)?)
+        .lines()
+        .enumerate()
+    {
+        let line = line?;
+        if line.starts_with('%') || line.trim().is_empty() {
+            continue;
+        }
+        if std::mem::take(&mut size_line) {
+            continue;
+        }
+        let mut entry = line.split_whitespace();
+        match (entry.next().and_then(kept), entry.next().and_then(kept)) {
+            (Some(row), Some(col)) => count += (row && col) as usize,
+            _ => return Err(invalid_data(file, num + 1, "invalid entry")),
+        }
+    }
+    Ok(count)
+}
 
-    let mats: HashMap<String, grb::Matrix> = mat_files
+/// Read a MatrixMarket file.
+fn read_matrix(file: &Path) -> Result<grb::Matrix, String> {
+    let mut mat = grb::Matrix::null();
+    let c_file = CString::new(file.to_str().unwrap()).unwrap();
+    let mode = CString::new("r").unwrap();
+    unsafe {
+        let f = libc::fopen(c_file.as_ptr(), mode.as_ptr());
+        if f.is_null() {
+            return Err(format!("unable to open {}", file.display()));
+        }
+        let code = LAGraph_MMRead(&mut mat as *mut grb::Matrix, f, std::ptr::null_mut());
+        libc::fclose(f);
+        if code != 0 {
+            return Err(format!(
+                "unable to load matrix in {} (error {})",
+                file.display(),
+                code
+            ));
+        }
+    }
+    mat.wait()?;
+    Ok(mat)
+}
+
+/// Load the dataset directory. Only the sizes of the label matrices are read,
+/// the matrices themselves are read on first use.
+///
+/// Vertex ids in `vertices.txt` are the 1-based rows and columns of the
+/// vertices in the matrix files, or 0-based when the file starts with a
+/// `% base 0` line. They may have gaps: the vertices then get dense matrix
+/// indices in the order of their ids and only their rows and columns are
+/// extracted from the matrices. The sizes of such labels count only the
+/// extracted entries, which reads their files once.
+pub fn load_dir(path: &Path) -> io::Result<Graph> {
+    let vertices = path.join("vertices.txt");
+    let base = read_base(&vertices)?;
+    let mut ids = read_ids(&vertices)?;
+    ids.sort_by_key(|(_, id)| *id);
+    if let Some((name, id)) = ids.first().filter(|(_, id)| *id < base) {
+        let msg = format!(
+            "id {} of vertex {} needs a `% base 0` line starting the file",
+            id, name
+        );
+        return Err(invalid_data(&vertices, 1, msg));
+    }
+    let rows: Vec<grb::Index> = ids
         .iter()
-        .map(|(edge, file)| {
-            let mut mat = grb::Matrix(std::ptr::null_mut());
-            unsafe {
-                let c_file = CString::new(file.to_str().unwrap()).unwrap();
-                let mode = CString::new("r").unwrap();
-                let f = libc::fopen(c_file.as_ptr(), mode.as_ptr());
-                let code = LAGraph_MMRead(&mut mat as *mut grb::Matrix, f, std::ptr::null_mut());
-                assert_eq!(
-                    code,
-                    0,
-                    "unable to load matrix for {} in {} (error {})",
-                    edge,
-                    file.display(),
-                    code
-                );
-            };
-            (edge.clone(), mat)
-        })
+        .map(|(_, id)| (id - base) as grb::Index)
         .collect();
+    let verts: HashMap<String, usize> = ids
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (name, _))| (name, idx))
+        .collect();
+    let dense = rows
+        .iter()
+        .enumerate()
+        .all(|(idx, &row)| row == idx as grb::Index);
+    let min_dim = rows.last().map_or(0, |row| row + 1) as usize;
+
+    let mut nvals = HashMap::new();
+    let mut labels = HashMap::new();
+    for (edge, num) in read_ids(&path.join("edges.txt"))? {
+        let file = path.join(format!("{}.txt", num));
+        if !file.exists() {
+            let msg = format!("no matrix file {} for label {}", file.display(), edge);
+            return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+        }
+        let (nrows, ncols, edge_nvals) = read_header(&file)?;
+        if nrows != ncols || nrows < min_dim {
+            let msg = format!(
+                "matrix of {} is {}x{} but vertex ids need {}x{}",
+                edge, nrows, ncols, min_dim, min_dim
+            );
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", file.display(), msg),
+            ));
+        }
+        let extract = !dense || nrows != verts.len();
+        let edge_nvals = match extract {
+            true => count_entries(&file, &rows)?,
+            false => edge_nvals,
+        };
+        nvals.insert(edge.clone(), edge_nvals);
+        let label = Label {
+            mat: None,
+            file: Some(file),
+            extract,
+            used: 0,
+        };
+        labels.insert(edge, label);
+    }
+
+    Ok(Graph {
+        nvals,
+        labels: Mutex::new(labels),
+        tick: AtomicU64::default(),
+        names: vertex_names(&verts).unwrap(),
+        rows,
+        base,
+        verts,
+        transposed: Mutex::default(),
+        stats: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::write_plan;
+
+    #[test]
+    fn test_read_header() {
+        let path = std::env::temp_dir().join(format!("rpq-{}-header.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "%%MatrixMarket matrix coordinate pattern general\n%%GraphBLAS type bool\n% comment\n4 4 2\n1 2\n3 4\n",
+        )
+        .unwrap();
+        assert_eq!(read_header(&path).unwrap(), (4, 4, 2));
+        std::fs::write(
+            &path,
+            "%%MatrixMarket matrix coordinate pattern general\n4 4\n",
+        )
+        .unwrap();
+        assert!(read_header(&path)
+            .unwrap_err()
+            .to_string()
+            .ends_with(":2: invalid size line"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_dir_ids() {
+        let dir = std::env::temp_dir().join(format!("rpq-{}-ids", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("vertices.txt"),
+            "% base 0\n<c> 5\n<a> 0\n\n<b> 3\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("edges.txt"), "<x> 1\n").unwrap();
+        std::fs::write(
+            dir.join("1.txt"),
+            "%%MatrixMarket matrix coordinate pattern general\n6 6 3\n1 4\n2 4\n6 1\n",
+        )
+        .unwrap();
+        let graph = load_dir(&dir).unwrap();
+        assert_eq!(graph.dim(), 3);
+        assert_eq!(graph.verts["b"], 1);
+        assert_eq!(graph.vertex_name(2), "c");
+        assert_eq!(graph.rows, vec![0, 3, 5]);
+        // Only the entries between the vertices are counted.
+        assert_eq!(graph.nvals["x"], 2);
+
+        // Without the base line, ids are 1-based even when sparse.
+        std::fs::write(dir.join("vertices.txt"), "<c> 6\n<a> 1\n<b> 4\n").unwrap();
+        assert_eq!(load_dir(&dir).unwrap().rows, vec![0, 3, 5]);
+        std::fs::write(dir.join("vertices.txt"), "<b> 4\n<a> 0\n").unwrap();
+        assert!(load_dir(&dir).err().unwrap().to_string().ends_with(
+            "vertices.txt:1: id 0 of vertex a needs a `% base 0` line starting the file"
+        ));
+
+        std::fs::write(dir.join("vertices.txt"), "<a> 1\n<b> 1\n").unwrap();
+        assert!(load_dir(&dir)
+            .err()
+            .unwrap()
+            .to_string()
+            .ends_with("vertices.txt:2: duplicate id 1 (first on line 1)"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_evict() {
+        crate::eval::init_test();
+        let dir = std::env::temp_dir().join(format!("rpq-{}-evict", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("vertices.txt"), "<a> 1\n<b> 2\n").unwrap();
+        std::fs::write(dir.join("edges.txt"), "<x> 1\n").unwrap();
+        std::fs::write(
+            dir.join("1.txt"),
+            "%%MatrixMarket matrix coordinate pattern general\n2 2 1\n1 2\n",
+        )
+        .unwrap();
+        let graph = load_dir(&dir).unwrap();
+        let mat = graph.matrix("x").unwrap();
+        let transposed = graph.transposed("x").unwrap();
+        graph.evict(0);
+        // Matrices got before stay usable until dropped.
+        assert_eq!(mat.pairs().unwrap(), vec![(0, 1)]);
+        assert_eq!(transposed.pairs().unwrap(), vec![(1, 0)]);
+        assert!(!Arc::ptr_eq(&mat, &graph.matrix("x").unwrap()));
+        assert_eq!(graph.matrix("x").unwrap().pairs().unwrap(), vec![(0, 1)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_update_edges() {
+        crate::eval::init_test();
+        let verts = HashMap::from([("a".to_string(), 0), ("b".to_string(), 1)]);
+        let edges = HashMap::from([("x".to_string(), vec![(0, 1)])]);
+        let mut graph = Graph::new(verts, edges).unwrap();
+        graph.set_stats(Some(Arc::default()));
+        assert_eq!(graph.add_vertex("b"), Ok(1));
+        assert!(!graph.insert_edge("a", "x", "b").unwrap());
+        assert!(graph.stats().is_some());
+
+        assert!(graph.insert_edge("b", "y", "c").unwrap());
+        assert!(graph.stats().is_none());
+        assert_eq!(graph.dim(), 3);
+        assert_eq!(graph.vertex_name(2), "c");
+        assert_eq!(graph.labels(), vec!["x", "y"]);
+        assert_eq!(graph.nvals["y"], 1);
+        assert_eq!(graph.matrix("x").unwrap().nrows(), Ok(3));
+        assert_eq!(graph.matrix("y").unwrap().pairs(), Ok(vec![(1, 2)]));
+        assert_eq!(graph.add_vertex("d"), Ok(3));
+        assert_eq!(graph.transposed("y").unwrap().nrows(), Ok(4));
+
+        assert!(graph.delete_edge("a", "x", "b").unwrap());
+        assert!(!graph.delete_edge("a", "x", "b").unwrap());
+        assert!(!graph.delete_edge("a", "z", "b").unwrap());
+        assert!(!graph.delete_edge("a", "x", "e").unwrap());
+        assert_eq!(graph.nvals["x"], 0);
+        assert_eq!(graph.matrix("x").unwrap().pairs(), Ok(vec![]));
+    }
 
-    Ok(Graph { nvals, mats, verts })
+    #[test]
+    fn test_write_back() {
+        crate::eval::init_test();
+        let dir = std::env::temp_dir().join(format!("rpq-{}-write-back", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("vertices.txt"), "% base 0\n<a> 0\n<b> 4\n").unwrap();
+        std::fs::write(dir.join("edges.txt"), "<x> 7\n").unwrap();
+        std::fs::write(
+            dir.join("7.txt"),
+            "%%MatrixMarket matrix coordinate pattern general\n6 6 2\n1 5\n2 5\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("queries.txt"), "1,<a> <x> ?d\n").unwrap();
+        std::fs::write(dir.join("stats.tsv"), "modified\t0\n").unwrap();
+        std::fs::create_dir(dir.join("plans")).unwrap();
+        std::fs::write(dir.join("plans").join("1.txt"), "1,<x>\n").unwrap();
+        std::os::unix::fs::symlink("queries.txt", dir.join("latest.txt")).unwrap();
+        let mut graph = load_dir(&dir).unwrap();
+        assert!(graph.insert_edge("b", "x", "c").unwrap());
+        graph.write_back(&dir).unwrap();
+
+        let mut files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                "1.txt",
+                "edges.txt",
+                "latest.txt",
+                "plans",
+                "queries.txt",
+                "vertices.txt"
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("plans").join("1.txt")).unwrap(),
+            "1,<x>\n"
+        );
+        assert_eq!(
+            std::fs::read_link(dir.join("latest.txt")).unwrap(),
+            Path::new("queries.txt")
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("vertices.txt")).unwrap(),
+            "% base 0\n<a> 0\n<b> 4\n<c> 5\n"
+        );
+        let graph = load_dir(&dir).unwrap();
+        assert_eq!(graph.rows, vec![0, 4, 5]);
+        assert_eq!(graph.verts["c"], 2);
+        assert_eq!(graph.matrix("x").unwrap().pairs(), Ok(vec![(0, 1), (1, 2)]));
+        assert!(!dir
+            .with_file_name(format!(".rpq-{}-write-back.old", std::process::id()))
+            .exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_load_dir() {
+        crate::eval::init_test();
+        let dir = std::env::temp_dir().join(format!("rpq-{}-save", std::process::id()));
+        let names = ["a", "b c", "<d>", "\"e\""];
+        let verts: HashMap<String, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.to_string(), idx))
+            .collect();
+        let edges = HashMap::from([
+            ("x".to_string(), vec![(0, 1), (1, 2), (3, 3)]),
+            ("y z".to_string(), vec![(2, 0)]),
+            ("<w>".to_string(), vec![]),
+        ]);
+        let graph = Graph::new(verts, edges).unwrap();
+        save_dir(&graph, &dir).unwrap();
+        let loaded = load_dir(&dir).unwrap();
+        assert_eq!(loaded.verts, graph.verts);
+        assert_eq!(loaded.labels(), graph.labels());
+        for label in graph.labels() {
+            assert_eq!(loaded.edges(&label), graph.edges(&label));
+            assert_eq!(loaded.nvals[&label], graph.nvals[&label]);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_endpoints() {
+        crate::eval::init_test();
+        let verts = HashMap::from([("a".to_string(), 0), ("b".to_string(), 1)]);
+        let edges = HashMap::from([("x".to_string(), vec![(0, 1)])]);
+        let graph = Graph::new(verts, edges).unwrap();
+        let plan = |query: &str| {
+            graph
+                .run(query.parse().unwrap())
+                .map(|expr| write_plan(&expr))
+        };
+        assert_eq!(
+            plan("<a> <x>* <b>"),
+            Ok("(/ {a} (/ (* <x>) {b}))".to_string())
+        );
+        assert_eq!(plan("?s <x> <b>"), Ok("(/ <x> {b})".to_string()));
+        assert_eq!(
+            plan("<a> <x>? ?d"),
+            Err("optional (?) patterns are not supported yet".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_id_line() {
+        assert_eq!(parse_id_line("<a b> 3"), Ok(("a b".to_string(), 3)));
+        assert_eq!(
+            parse_id_line(r#""say \"hi\"" 7"#),
+            Ok((r#"say "hi""#.to_string(), 7))
+        );
+        assert_eq!(parse_id_line("<> 1"), Ok(("".to_string(), 1)));
+        assert_eq!(parse_id_line("<a"), Err("unterminated <name>".to_string()));
+        assert_eq!(
+            parse_id_line("<a>"),
+            Err("expected whitespace after the name".to_string())
+        );
+        assert_eq!(parse_id_line("<a> x"), Err("invalid id: x".to_string()));
+        assert_eq!(
+            parse_id_line("a 1"),
+            Err("expected <name> or \"name\"".to_string())
+        );
+    }
 }
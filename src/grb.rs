@@ -1,11 +1,910 @@
 use std::ptr::null_mut;
 
+pub type Index = u64;
+
 #[repr(C)]
 #[derive(Clone)]
 pub struct Matrix(pub *mut libc::c_void);
 
+// GraphBLAS objects may be used from several threads as long as they have no
+// pending work and are only read concurrently, see [`Matrix::wait`].
+unsafe impl Send for Matrix {}
+unsafe impl Sync for Matrix {}
+
 impl Matrix {
     pub fn null() -> Matrix {
         Matrix(null_mut())
     }
+
+    /// Build a `size x size` boolean matrix with the given entries,
+    /// duplicates allowed.
+    pub fn build(size: Index, pairs: &[(Index, Index)]) -> Result<Matrix, String> {
+        let mut res = Matrix::null();
+        let (rows, cols): (Vec<Index>, Vec<Index>) = pairs.iter().copied().unzip();
+        let vals = vec![true; pairs.len()];
+        unsafe {
+            check(
+                GrB_Matrix_new(&mut res.0, GrB_BOOL, size, size),
+                "GrB_Matrix_new",
+            )?;
+            check(
+                GrB_Matrix_build_BOOL(
+                    res.0,
+                    rows.as_ptr(),
+                    cols.as_ptr(),
+                    vals.as_ptr(),
+                    pairs.len() as Index,
+                    GrB_LOR,
+                ),
+                "GrB_Matrix_build_BOOL",
+            )?;
+        }
+        Ok(res)
+    }
+
+    /// Build a boolean matrix from its compressed rows: the entries of row
+    /// `i` are in the columns `cols[rows[i]..rows[i + 1]]`.
+    pub fn import_csr(
+        nrows: Index,
+        ncols: Index,
+        rows: &[Index],
+        cols: &[Index],
+    ) -> Result<Matrix, String> {
+        let mut res = Matrix::null();
+        let vals = vec![true; cols.len()];
+        check(
+            unsafe {
+                GrB_Matrix_import_BOOL(
+                    &mut res.0,
+                    GrB_BOOL,
+                    nrows,
+                    ncols,
+                    rows.as_ptr(),
+                    cols.as_ptr(),
+                    vals.as_ptr(),
+                    rows.len() as Index,
+                    cols.len() as Index,
+                    vals.len() as Index,
+                    GRB_CSR_FORMAT,
+                )
+            },
+            "GrB_Matrix_import_BOOL",
+        )?;
+        Ok(res)
+    }
+
+    /// Get the compressed rows of the matrix, see [`Matrix::import_csr`].
+    pub fn export_csr(&self) -> Result<(Vec<Index>, Vec<Index>), String> {
+        let (mut rows_len, mut cols_len, mut vals_len) = (0, 0, 0);
+        check(
+            unsafe {
+                GrB_Matrix_exportSize(
+                    &mut rows_len,
+                    &mut cols_len,
+                    &mut vals_len,
+                    GRB_CSR_FORMAT,
+                    self.0,
+                )
+            },
+            "GrB_Matrix_exportSize",
+        )?;
+        let mut rows = vec![0; rows_len as usize];
+        let mut cols = vec![0; cols_len as usize];
+        let mut vals = vec![false; vals_len as usize];
+        check(
+            unsafe {
+                GrB_Matrix_export_BOOL(
+                    rows.as_mut_ptr(),
+                    cols.as_mut_ptr(),
+                    vals.as_mut_ptr(),
+                    &mut rows_len,
+                    &mut cols_len,
+                    &mut vals_len,
+                    GRB_CSR_FORMAT,
+                    self.0,
+                )
+            },
+            "GrB_Matrix_export_BOOL",
+        )?;
+        rows.truncate(rows_len as usize);
+        cols.truncate(cols_len as usize);
+        Ok((rows, cols))
+    }
+
+    pub fn dup(&self) -> Result<OwnedMatrix, String> {
+        let mut res = Matrix::null();
+        check(
+            unsafe { GrB_Matrix_dup(&mut res.0, self.0) },
+            "GrB_Matrix_dup",
+        )?;
+        Ok(OwnedMatrix(res))
+    }
+
+    /// Finish pending work so the matrix can be read by several threads.
+    pub fn wait(&self) -> Result<(), String> {
+        check(
+            unsafe { GrB_Matrix_wait(self.0, GRB_MATERIALIZE) },
+            "GrB_Matrix_wait",
+        )
+    }
+
+    pub fn nvals(&self) -> Result<Index, String> {
+        let mut n: Index = 0;
+        check(
+            unsafe { GrB_Matrix_nvals(&mut n, self.0) },
+            "GrB_Matrix_nvals",
+        )?;
+        Ok(n)
+    }
+
+    pub fn nrows(&self) -> Result<Index, String> {
+        let mut n: Index = 0;
+        check(
+            unsafe { GrB_Matrix_nrows(&mut n, self.0) },
+            "GrB_Matrix_nrows",
+        )?;
+        Ok(n)
+    }
+
+    pub fn ncols(&self) -> Result<Index, String> {
+        let mut n: Index = 0;
+        check(
+            unsafe { GrB_Matrix_ncols(&mut n, self.0) },
+            "GrB_Matrix_ncols",
+        )?;
+        Ok(n)
+    }
+
+    /// Build a new boolean matrix holding the transposition of this one.
+    pub fn transpose(&self) -> Result<Matrix, String> {
+        let mut res = Matrix::null();
+        unsafe {
+            check(
+                GrB_Matrix_new(&mut res.0, GrB_BOOL, self.ncols()?, self.nrows()?),
+                "GrB_Matrix_new",
+            )?;
+            check(
+                GrB_transpose(res.0, null_mut(), null_mut(), self.0, null_mut()),
+                "GrB_transpose",
+            )?;
+        }
+        Ok(res)
+    }
+
+    /// Change the dimensions, dropping entries outside of them.
+    pub fn resize(&self, nrows: Index, ncols: Index) -> Result<(), String> {
+        check(
+            unsafe { GrB_Matrix_resize(self.0, nrows, ncols) },
+            "GrB_Matrix_resize",
+        )
+    }
+
+    /// Whether the matrix has an entry at `row` and `col`.
+    pub fn contains(&self, row: Index, col: Index) -> Result<bool, String> {
+        let mut x = false;
+        match unsafe { GrB_Matrix_extractElement_BOOL(&mut x, self.0, row, col) } {
+            GRB_NO_VALUE => Ok(false),
+            info => check(info, "GrB_Matrix_extractElement_BOOL").map(|_| true),
+        }
+    }
+
+    /// Add an entry at `row` and `col`.
+    pub fn set(&self, row: Index, col: Index) -> Result<(), String> {
+        check(
+            unsafe { GrB_Matrix_setElement_BOOL(self.0, true, row, col) },
+            "GrB_Matrix_setElement_BOOL",
+        )
+    }
+
+    /// Remove the entry at `row` and `col`, if any.
+    pub fn remove(&self, row: Index, col: Index) -> Result<(), String> {
+        check(
+            unsafe { GrB_Matrix_removeElement(self.0, row, col) },
+            "GrB_Matrix_removeElement",
+        )
+    }
+
+    /// Build the matrix of the given rows and columns, in their order.
+    pub fn extract(&self, idx: &[Index]) -> Result<Matrix, String> {
+        let mut res = Matrix::null();
+        let size = idx.len() as Index;
+        unsafe {
+            check(
+                GrB_Matrix_new(&mut res.0, GrB_BOOL, size, size),
+                "GrB_Matrix_new",
+            )?;
+            check(
+                GrB_Matrix_extract(
+                    res.0,
+                    null_mut(),
+                    null_mut(),
+                    self.0,
+                    idx.as_ptr(),
+                    size,
+                    idx.as_ptr(),
+                    size,
+                    null_mut(),
+                ),
+                "GrB_Matrix_extract",
+            )?;
+        }
+        res.wait()?;
+        Ok(res)
+    }
+
+    /// Compute `self * other` over the boolean semiring.
+    pub fn mxm(&self, other: &Matrix) -> Result<OwnedMatrix, String> {
+        unsafe { self.mxm_semiring(other, GrB_BOOL, GrB_LOR_LAND_SEMIRING_BOOL) }
+    }
+
+    /// Compute `self * other` over `semiring` into a matrix of type `typ`.
+    ///
+    /// # Safety
+    /// `typ` and `semiring` must be valid GraphBLAS handles.
+    pub unsafe fn mxm_semiring(
+        &self,
+        other: &Matrix,
+        typ: *mut libc::c_void,
+        semiring: *mut libc::c_void,
+    ) -> Result<OwnedMatrix, String> {
+        let res = OwnedMatrix::new_typed(typ, self.nrows()?, other.ncols()?)?;
+        check(
+            GrB_mxm(
+                res.0 .0,
+                null_mut(),
+                null_mut(),
+                semiring,
+                self.0,
+                other.0,
+                null_mut(),
+            ),
+            "GrB_mxm",
+        )?;
+        Ok(res)
+    }
+
+    /// Combine entries of both matrices with `op` into a matrix of type `typ`.
+    ///
+    /// # Safety
+    /// `typ` and `op` must be valid GraphBLAS handles.
+    pub unsafe fn ewise_add(
+        &self,
+        other: &Matrix,
+        typ: *mut libc::c_void,
+        op: *mut libc::c_void,
+    ) -> Result<OwnedMatrix, String> {
+        let res = OwnedMatrix::new_typed(typ, self.nrows()?, self.ncols()?)?;
+        check(
+            GrB_Matrix_eWiseAdd_BinaryOp(
+                res.0 .0,
+                null_mut(),
+                null_mut(),
+                op,
+                self.0,
+                other.0,
+                null_mut(),
+            ),
+            "GrB_Matrix_eWiseAdd_BinaryOp",
+        )?;
+        Ok(res)
+    }
+
+    /// Convert every entry with the unary `op` into a matrix of type `typ`.
+    ///
+    /// # Safety
+    /// `typ` and `op` must be valid GraphBLAS handles.
+    pub unsafe fn apply(
+        &self,
+        typ: *mut libc::c_void,
+        op: *mut libc::c_void,
+    ) -> Result<OwnedMatrix, String> {
+        let res = OwnedMatrix::new_typed(typ, self.nrows()?, self.ncols()?)?;
+        check(
+            GrB_Matrix_apply(res.0 .0, null_mut(), null_mut(), op, self.0, null_mut()),
+            "GrB_Matrix_apply",
+        )?;
+        Ok(res)
+    }
+
+    /// Reduce all entries of an integer matrix with `monoid`.
+    ///
+    /// # Safety
+    /// `monoid` must be a valid GraphBLAS monoid handle.
+    pub unsafe fn reduce_u64(&self, monoid: *mut libc::c_void) -> Result<u64, String> {
+        let mut res = 0;
+        check(
+            GrB_Matrix_reduce_UINT64(&mut res, null_mut(), monoid, self.0, null_mut()),
+            "GrB_Matrix_reduce_UINT64",
+        )?;
+        Ok(res)
+    }
+
+    /// Get the row and column indices of all entries.
+    pub fn pairs(&self) -> Result<Vec<(Index, Index)>, String> {
+        let mut n = self.nvals()?;
+        let mut rows = vec![0; n as usize];
+        let mut cols = vec![0; n as usize];
+        check(
+            unsafe {
+                GrB_Matrix_extractTuples_BOOL(
+                    rows.as_mut_ptr(),
+                    cols.as_mut_ptr(),
+                    null_mut(),
+                    &mut n,
+                    self.0,
+                )
+            },
+            "GrB_Matrix_extractTuples_BOOL",
+        )?;
+        Ok(rows.into_iter().zip(cols).take(n as usize).collect())
+    }
+
+    /// Add all entries of `other` to this matrix.
+    pub fn union(&mut self, other: &Matrix) -> Result<(), String> {
+        check(
+            unsafe {
+                GrB_Matrix_eWiseAdd_BinaryOp(
+                    self.0,
+                    null_mut(),
+                    null_mut(),
+                    GrB_LOR,
+                    self.0,
+                    other.0,
+                    null_mut(),
+                )
+            },
+            "GrB_Matrix_eWiseAdd_BinaryOp",
+        )
+    }
+
+    /// Build a matrix with the entries of this one which are absent in `other`.
+    pub fn without(&self, other: &Matrix) -> Result<OwnedMatrix, String> {
+        let res = OwnedMatrix::new(self.nrows()?, self.ncols()?)?;
+        check(
+            unsafe {
+                GrB_Matrix_apply(
+                    res.0 .0,
+                    other.0,
+                    null_mut(),
+                    GrB_IDENTITY_BOOL,
+                    self.0,
+                    GrB_DESC_RSC,
+                )
+            },
+            "GrB_Matrix_apply",
+        )?;
+        Ok(res)
+    }
+
+    /// Whether both matrices have the same type, entries and values.
+    pub fn is_equal(&self, other: &Matrix) -> Result<bool, String> {
+        let mut res = false;
+        check(
+            unsafe { crate::eval::LAGraph_Matrix_IsEqual(&mut res, self.0, other.0, null_mut()) },
+            "LAGraph_Matrix_IsEqual",
+        )?;
+        Ok(res)
+    }
+}
+
+/// A matrix freed when dropped, unlike [`Matrix`] handles shared with the
+/// graph or LAGraph plans.
+pub struct OwnedMatrix(pub Matrix);
+
+impl OwnedMatrix {
+    pub fn new(nrows: Index, ncols: Index) -> Result<Self, String> {
+        unsafe { OwnedMatrix::new_typed(GrB_BOOL, nrows, ncols) }
+    }
+
+    /// # Safety
+    /// `typ` must be a valid GraphBLAS type handle.
+    pub unsafe fn new_typed(
+        typ: *mut libc::c_void,
+        nrows: Index,
+        ncols: Index,
+    ) -> Result<Self, String> {
+        let mut res = Matrix::null();
+        check(
+            GrB_Matrix_new(&mut res.0, typ, nrows, ncols),
+            "GrB_Matrix_new",
+        )?;
+        Ok(OwnedMatrix(res))
+    }
+
+    /// Build the `size x size` integer matrix with `value` on its diagonal.
+    pub fn diagonal_u64(size: Index, value: u64) -> Result<Self, String> {
+        let res = unsafe { OwnedMatrix::new_typed(GrB_UINT64, size, size)? };
+        let idx: Vec<Index> = (0..size).collect();
+        let vals = vec![value; idx.len()];
+        check(
+            unsafe {
+                GrB_Matrix_build_UINT64(
+                    res.0 .0,
+                    idx.as_ptr(),
+                    idx.as_ptr(),
+                    vals.as_ptr(),
+                    size,
+                    GrB_PLUS_UINT64,
+                )
+            },
+            "GrB_Matrix_build_UINT64",
+        )?;
+        Ok(res)
+    }
+
+    /// Build the `size x size` identity matrix.
+    pub fn identity(size: Index) -> Result<Self, String> {
+        let res = OwnedMatrix::new(size, size)?;
+        let idx: Vec<Index> = (0..size).collect();
+        let vals = vec![true; idx.len()];
+        check(
+            unsafe {
+                GrB_Matrix_build_BOOL(
+                    res.0 .0,
+                    idx.as_ptr(),
+                    idx.as_ptr(),
+                    vals.as_ptr(),
+                    size,
+                    GrB_LOR,
+                )
+            },
+            "GrB_Matrix_build_BOOL",
+        )?;
+        Ok(res)
+    }
+}
+
+impl std::ops::Deref for OwnedMatrix {
+    type Target = Matrix;
+
+    fn deref(&self) -> &Matrix {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for OwnedMatrix {
+    fn deref_mut(&mut self) -> &mut Matrix {
+        &mut self.0
+    }
+}
+
+impl Drop for OwnedMatrix {
+    fn drop(&mut self) {
+        unsafe {
+            GrB_Matrix_free(&mut self.0 .0);
+        }
+    }
+}
+
+/// A sparse boolean vector owned by Rust.
+pub struct Vector(pub *mut libc::c_void);
+
+unsafe impl Send for Vector {}
+
+impl Vector {
+    pub fn new(size: Index) -> Result<Vector, String> {
+        let mut res = Vector(null_mut());
+        check(
+            unsafe { GrB_Vector_new(&mut res.0, GrB_BOOL, size) },
+            "GrB_Vector_new",
+        )?;
+        Ok(res)
+    }
+
+    pub fn dup(&self) -> Result<Vector, String> {
+        let mut res = Vector(null_mut());
+        check(
+            unsafe { GrB_Vector_dup(&mut res.0, self.0) },
+            "GrB_Vector_dup",
+        )?;
+        Ok(res)
+    }
+
+    pub fn size(&self) -> Result<Index, String> {
+        let mut n: Index = 0;
+        check(
+            unsafe { GrB_Vector_size(&mut n, self.0) },
+            "GrB_Vector_size",
+        )?;
+        Ok(n)
+    }
+
+    pub fn nvals(&self) -> Result<Index, String> {
+        let mut n: Index = 0;
+        check(
+            unsafe { GrB_Vector_nvals(&mut n, self.0) },
+            "GrB_Vector_nvals",
+        )?;
+        Ok(n)
+    }
+
+    pub fn set(&mut self, idx: Index) -> Result<(), String> {
+        check(
+            unsafe { GrB_Vector_setElement_BOOL(self.0, true, idx) },
+            "GrB_Vector_setElement_BOOL",
+        )
+    }
+
+    pub fn contains(&self, idx: Index) -> Result<bool, String> {
+        let mut x = false;
+        match unsafe { GrB_Vector_extractElement_BOOL(&mut x, self.0, idx) } {
+            GRB_NO_VALUE => Ok(false),
+            info => check(info, "GrB_Vector_extractElement_BOOL").map(|_| true),
+        }
+    }
+
+    /// Get the indices of all entries.
+    pub fn indices(&self) -> Result<Vec<Index>, String> {
+        let mut n = self.nvals()?;
+        let mut idx = vec![0; n as usize];
+        check(
+            unsafe { GrB_Vector_extractTuples_BOOL(idx.as_mut_ptr(), null_mut(), &mut n, self.0) },
+            "GrB_Vector_extractTuples_BOOL",
+        )?;
+        idx.truncate(n as usize);
+        Ok(idx)
+    }
+
+    /// Compute `self * mat` over the boolean semiring.
+    pub fn vxm(&self, mat: &Matrix) -> Result<Vector, String> {
+        let res = Vector::new(mat.ncols()?)?;
+        check(
+            unsafe {
+                GrB_vxm(
+                    res.0,
+                    null_mut(),
+                    null_mut(),
+                    GrB_LOR_LAND_SEMIRING_BOOL,
+                    self.0,
+                    mat.0,
+                    null_mut(),
+                )
+            },
+            "GrB_vxm",
+        )?;
+        Ok(res)
+    }
+
+    /// Add all entries of `other` to this vector.
+    pub fn union(&mut self, other: &Vector) -> Result<(), String> {
+        check(
+            unsafe {
+                GrB_Vector_eWiseAdd_BinaryOp(
+                    self.0,
+                    null_mut(),
+                    null_mut(),
+                    GrB_LOR,
+                    self.0,
+                    other.0,
+                    null_mut(),
+                )
+            },
+            "GrB_Vector_eWiseAdd_BinaryOp",
+        )
+    }
+
+    /// Build a vector with the entries of this one which are absent in `other`.
+    pub fn without(&self, other: &Vector) -> Result<Vector, String> {
+        let res = Vector::new(self.size()?)?;
+        check(
+            unsafe {
+                GrB_Vector_apply(
+                    res.0,
+                    other.0,
+                    null_mut(),
+                    GrB_IDENTITY_BOOL,
+                    self.0,
+                    GrB_DESC_RSC,
+                )
+            },
+            "GrB_Vector_apply",
+        )?;
+        Ok(res)
+    }
+
+    /// Build a `size x size` matrix holding this vector as the row `row`.
+    pub fn to_row(&self, row: Index, size: Index) -> Result<Matrix, String> {
+        let mut res = Matrix::null();
+        unsafe {
+            check(
+                GrB_Matrix_new(&mut res.0, GrB_BOOL, size, size),
+                "GrB_Matrix_new",
+            )?;
+            check(
+                GrB_Row_assign(
+                    res.0,
+                    null_mut(),
+                    null_mut(),
+                    self.0,
+                    row,
+                    GrB_ALL,
+                    size,
+                    null_mut(),
+                ),
+                "GrB_Row_assign",
+            )?;
+        }
+        Ok(res)
+    }
+}
+
+impl Drop for Vector {
+    fn drop(&mut self) {
+        unsafe {
+            GrB_Vector_free(&mut self.0);
+        }
+    }
+}
+
+const GRB_NO_VALUE: libc::c_int = 1;
+const GRB_MATERIALIZE: libc::c_int = 1;
+const GRB_CSR_FORMAT: libc::c_int = 0;
+const GXB_NTHREADS: libc::c_int = 7086;
+
+/// Number of threads GraphBLAS uses unless limited by a context.
+pub fn global_threads() -> Result<i32, String> {
+    let mut n = 0;
+    check(
+        unsafe { GxB_Global_Option_get_INT32(GXB_NTHREADS, &mut n) },
+        "GxB_Global_Option_get_INT32",
+    )?;
+    Ok(n)
+}
+
+thread_local! {
+    static CONTEXT: std::cell::RefCell<Option<Context>> = std::cell::RefCell::new(None);
+}
+
+/// GraphBLAS context engaged by a thread, freed when the thread exits.
+struct Context(*mut libc::c_void);
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe {
+            GxB_Context_disengage(self.0);
+            GxB_Context_free(&mut self.0);
+        }
+    }
+}
+
+/// Limit GraphBLAS operations called from the current thread to `nthreads`
+/// threads.
+pub fn engage_threads(nthreads: i32) -> Result<(), String> {
+    let mut ctx = Context(null_mut());
+    unsafe {
+        check(GxB_Context_new(&mut ctx.0), "GxB_Context_new")?;
+        check(
+            GxB_Context_set_INT32(ctx.0, GXB_NTHREADS, nthreads),
+            "GxB_Context_set_INT32",
+        )?;
+        check(GxB_Context_engage(ctx.0), "GxB_Context_engage")?;
+    }
+    CONTEXT.with(|context| *context.borrow_mut() = Some(ctx));
+    Ok(())
+}
+
+pub fn check(info: libc::c_int, func: &str) -> Result<(), String> {
+    match info {
+        0 => Ok(()),
+        code => Err(format!("{} failed (error {})", func, code)),
+    }
+}
+
+#[link(name = "graphblas")]
+extern "C" {
+    pub static GrB_BOOL: *mut libc::c_void;
+    pub static GrB_LOR: *mut libc::c_void;
+    pub static GrB_IDENTITY_BOOL: *mut libc::c_void;
+    pub static GrB_LOR_LAND_SEMIRING_BOOL: *mut libc::c_void;
+    pub static GrB_DESC_RSC: *mut libc::c_void;
+    pub static GrB_UINT64: *mut libc::c_void;
+    pub static GrB_IDENTITY_UINT64: *mut libc::c_void;
+    pub static GrB_PLUS_UINT64: *mut libc::c_void;
+    pub static GrB_MIN_UINT64: *mut libc::c_void;
+    pub static GrB_PLUS_MONOID_UINT64: *mut libc::c_void;
+    pub static GrB_MAX_MONOID_UINT64: *mut libc::c_void;
+    pub static GrB_PLUS_TIMES_SEMIRING_UINT64: *mut libc::c_void;
+    pub static GrB_MIN_PLUS_SEMIRING_UINT64: *mut libc::c_void;
+    pub static GrB_ALL: *const Index;
+
+    pub fn GrB_Matrix_new(
+        mat: *mut *mut libc::c_void,
+        typ: *mut libc::c_void,
+        nrows: Index,
+        ncols: Index,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_wait(mat: *mut libc::c_void, mode: libc::c_int) -> libc::c_int;
+    pub fn GrB_Matrix_dup(res: *mut *mut libc::c_void, mat: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Matrix_free(mat: *mut *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Matrix_nvals(n: *mut Index, mat: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Matrix_build_BOOL(
+        mat: *mut libc::c_void,
+        rows: *const Index,
+        cols: *const Index,
+        vals: *const bool,
+        nvals: Index,
+        dup: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_build_UINT64(
+        mat: *mut libc::c_void,
+        rows: *const Index,
+        cols: *const Index,
+        vals: *const u64,
+        nvals: Index,
+        dup: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_reduce_UINT64(
+        res: *mut u64,
+        accum: *mut libc::c_void,
+        monoid: *mut libc::c_void,
+        mat: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_extractTuples_BOOL(
+        rows: *mut Index,
+        cols: *mut Index,
+        vals: *mut bool,
+        n: *mut Index,
+        mat: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_import_BOOL(
+        mat: *mut *mut libc::c_void,
+        typ: *mut libc::c_void,
+        nrows: Index,
+        ncols: Index,
+        rows: *const Index,
+        cols: *const Index,
+        vals: *const bool,
+        rows_len: Index,
+        cols_len: Index,
+        vals_len: Index,
+        format: libc::c_int,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_exportSize(
+        rows_len: *mut Index,
+        cols_len: *mut Index,
+        vals_len: *mut Index,
+        format: libc::c_int,
+        mat: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_export_BOOL(
+        rows: *mut Index,
+        cols: *mut Index,
+        vals: *mut bool,
+        rows_len: *mut Index,
+        cols_len: *mut Index,
+        vals_len: *mut Index,
+        format: libc::c_int,
+        mat: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_extract(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        mat: *mut libc::c_void,
+        rows: *const Index,
+        nrows: Index,
+        cols: *const Index,
+        ncols: Index,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_resize(mat: *mut libc::c_void, nrows: Index, ncols: Index) -> libc::c_int;
+    pub fn GrB_Matrix_extractElement_BOOL(
+        x: *mut bool,
+        mat: *mut libc::c_void,
+        row: Index,
+        col: Index,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_setElement_BOOL(
+        mat: *mut libc::c_void,
+        x: bool,
+        row: Index,
+        col: Index,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_removeElement(mat: *mut libc::c_void, row: Index, col: Index) -> libc::c_int;
+    pub fn GrB_Matrix_nrows(n: *mut Index, mat: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Matrix_ncols(n: *mut Index, mat: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_transpose(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        mat: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_mxm(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        semiring: *mut libc::c_void,
+        lhs: *mut libc::c_void,
+        rhs: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_eWiseAdd_BinaryOp(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        op: *mut libc::c_void,
+        lhs: *mut libc::c_void,
+        rhs: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Matrix_apply(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        op: *mut libc::c_void,
+        mat: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+
+    pub fn GrB_Row_assign(
+        mat: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        vec: *mut libc::c_void,
+        row: Index,
+        cols: *const Index,
+        ncols: Index,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+
+    pub fn GrB_Vector_new(
+        vec: *mut *mut libc::c_void,
+        typ: *mut libc::c_void,
+        size: Index,
+    ) -> libc::c_int;
+    pub fn GrB_Vector_dup(res: *mut *mut libc::c_void, vec: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Vector_free(vec: *mut *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Vector_size(n: *mut Index, vec: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Vector_nvals(n: *mut Index, vec: *mut libc::c_void) -> libc::c_int;
+    pub fn GrB_Vector_setElement_BOOL(vec: *mut libc::c_void, x: bool, idx: Index) -> libc::c_int;
+    pub fn GrB_Vector_extractElement_BOOL(
+        x: *mut bool,
+        vec: *mut libc::c_void,
+        idx: Index,
+    ) -> libc::c_int;
+    pub fn GrB_Vector_extractTuples_BOOL(
+        idx: *mut Index,
+        vals: *mut bool,
+        n: *mut Index,
+        vec: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Vector_eWiseAdd_BinaryOp(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        op: *mut libc::c_void,
+        lhs: *mut libc::c_void,
+        rhs: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_Vector_apply(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        op: *mut libc::c_void,
+        vec: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+    pub fn GrB_vxm(
+        res: *mut libc::c_void,
+        mask: *mut libc::c_void,
+        accum: *mut libc::c_void,
+        semiring: *mut libc::c_void,
+        vec: *mut libc::c_void,
+        mat: *mut libc::c_void,
+        desc: *mut libc::c_void,
+    ) -> libc::c_int;
+
+    pub fn GxB_Global_Option_get_INT32(field: libc::c_int, value: *mut i32) -> libc::c_int;
+    pub fn GxB_Context_new(ctx: *mut *mut libc::c_void) -> libc::c_int;
+    pub fn GxB_Context_free(ctx: *mut *mut libc::c_void) -> libc::c_int;
+    pub fn GxB_Context_set_INT32(
+        ctx: *mut libc::c_void,
+        field: libc::c_int,
+        value: i32,
+    ) -> libc::c_int;
+    pub fn GxB_Context_engage(ctx: *mut libc::c_void) -> libc::c_int;
+    pub fn GxB_Context_disengage(ctx: *mut libc::c_void) -> libc::c_int;
 }
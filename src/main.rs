@@ -1,55 +1,327 @@
+mod csv;
+mod dot;
+mod edgelist;
 mod eval;
 mod graph;
 mod grb;
+mod ntriples;
+mod options;
 mod plan;
+mod profile;
 mod query;
+mod semiring;
+mod snapshot;
+mod standing;
+mod stats;
+mod update;
+mod witness;
 
 use crate::{
-    eval::{eval, LAGraph_Init},
-    plan::{make_rules, RandomCostFn},
-    query::Query,
+    eval::{
+        eval, eval_cancellable, eval_parallel, eval_profiled, eval_profiled_rpq, set_threads,
+        thread_pool, threads, Cancel, EvalError, LAGraph_Init,
+    },
+    options::{Options, USAGE},
+    plan::{
+        count_plans, enumerate_plans, extract_rooted, make_rules, parse_plan, write_plan,
+        EstimateCostFn, RandomCostFn,
+    },
+    query::{parse_hints, Hint, Query, Vertex},
+    semiring::{eval_semiring, Semiring},
+    standing::StandingQuery,
+    witness::find_witness,
 };
-use egg::{RecExpr, Runner};
+use egg::{Id, RecExpr, Runner};
 use graph::Graph;
 use plan::Plan;
-use std::{ops::Div, path::Path, time::Duration};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    ops::Div,
+    path::Path,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+/// Cancelled on the first interrupt, stopping the benchmark with the results
+/// gathered so far.
+static INTERRUPT: OnceLock<Cancel> = OnceLock::new();
+
+/// Workers evaluating plans with `--parallel`, rebuilt whenever the number
+/// of GraphBLAS threads changes.
+static POOL: RwLock<Option<rayon::ThreadPool>> = RwLock::new(None);
+
+/// Use `nthreads` GraphBLAS threads for the following evaluations.
+fn use_threads(nthreads: i32, options: &Options) -> Result<(), String> {
+    set_threads(nthreads)?;
+    if let Some(workers) = options.parallel {
+        *POOL.write().unwrap() = Some(thread_pool(workers)?);
+    }
+    Ok(())
+}
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    if let Some(interrupt) = INTERRUPT.get() {
+        interrupt.cancel();
+    }
+    // A second interrupt kills the process.
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+}
+
+/// Token stopping an evaluation after `--timeout`, over `--memory-budget` or
+/// on interrupt.
+fn limits(options: &Options) -> Cancel {
+    let mut cancel = INTERRUPT.get_or_init(Cancel::default).clone();
+    if let Some(timeout) = options.timeout {
+        cancel = cancel.with_timeout(timeout);
+    }
+    if let Some(budget) = options.memory_budget {
+        cancel = cancel.with_budget(budget);
+    }
+    cancel
+}
+
+/// Whether the limits need the node by node evaluator, which can be stopped
+/// between nodes, instead of a single `LAGraph_RPQMatrix` call.
+fn node_by_node(options: &Options) -> bool {
+    options.timeout.is_some() || options.memory_budget.is_some()
+}
+
+/// Describe the evaluator [`evaluate`] uses, as timings of different
+/// evaluators are not comparable.
+fn engine(options: &Options) -> &'static str {
+    if options.parallel.is_some() {
+        "parallel node by node evaluation"
+    } else if node_by_node(options) {
+        "node by node evaluation"
+    } else {
+        "LAGraph_RPQMatrix"
+    }
+}
+
+/// Evaluate a plan within the [`limits`].
+fn evaluate(graph: &Graph, plan: RecExpr<Plan>, options: &Options) -> Result<usize, EvalError> {
+    let cancel = limits(options);
+    if let Some(pool) = POOL.read().unwrap().as_ref() {
+        eval_parallel(graph, plan, &cancel, pool)
+    } else if node_by_node(options) {
+        eval_cancellable(graph, plan, &cancel)
+    } else {
+        cancel.check()?;
+        Ok(eval(graph, plan)?)
+    }
+}
+
+type QueryLine = (String, Query, Result<Vec<Hint>, String>);
 
 /// Read queries from file.
 ///
-/// The file should contain lines satisfying the following pattern: `<number>,<src> <pattern> <dest>`.
+/// The file should contain lines satisfying the following pattern: `<number>,<src> <pattern> <dest>`,
+/// optionally followed by `,<hints>` (see [`parse_hints`]).
 ///
 /// # Query file example
 /// ```
-/// 1,?sub <references>/<cite>/<creator> ?obj
-/// 2,?sub (<coauthor>)+ <Fiorenza_Summerset>
-/// 3,<Article1659> (<references>/<cite>)* ?obj
+/// 1,?sub <references>/<cite>/<creator> ?obj,witness=Article1659,Bea_Blumenthal
+/// 2,?sub (<coauthor>)+ <Fiorenza_Summerset>,from-dest no-distribute threads=4
+/// 3,<Article1659> (<references>/<cite>)* ?obj,plan (vxm {Article1659} (* (/ <references> <cite>)))
 /// ```
-fn read_queries(file: &Path) -> Vec<Query> {
+fn read_queries(file: &Path) -> Vec<QueryLine> {
     std::fs::read_to_string(file)
         .expect("unable to load queries")
         .lines()
-        .filter_map(|line| Some(line.split(',').nth(1)?.to_string()))
-        .filter_map(|query| query.parse::<Query>().ok())
+        .filter_map(|line| {
+            let mut splits = line.splitn(3, ',');
+            let num = splits.next()?.trim().to_string();
+            let query = splits.next()?.parse::<Query>().ok()?;
+            let hints = splits.next().map_or(Ok(vec![]), parse_hints);
+            Some((num, query, hints))
+        })
+        .collect()
+}
+
+/// Read plans saved with `--save-plans`: lines `<number>,<plan>`, where the
+/// plan is written by [`write_plan`].
+fn read_plans(file: &Path) -> Vec<(String, Result<RecExpr<Plan>, String>)> {
+    std::fs::read_to_string(file)
+        .expect("unable to load plans")
+        .lines()
+        .filter_map(|line| {
+            let (num, plan) = line.split_once(',')?;
+            Some((num.trim().to_string(), parse_plan(plan)))
+        })
         .collect()
 }
 
+/// Evaluate saved plans skipping the query parser and equality saturation.
+fn replay(graph: &Graph, options: &Options) {
+    println!("Engine: {}", engine(options));
+    read_plans(&options.queries)
+        .into_iter()
+        .for_each(|(num, plan)| {
+            let plan = match plan.and_then(|plan| graph.resolve(&plan)) {
+                Ok(plan) => plan,
+                Err(msg) => {
+                    println!("unable to load plan {}: {}", num, msg);
+                    return;
+                }
+            };
+            let results = (0..options.runs.unwrap_or(1).max(1))
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    let answer = evaluate(graph, plan.clone(), options)?;
+                    Ok((answer, start.elapsed()))
+                })
+                .collect::<Result<Vec<_>, EvalError>>();
+            match results {
+                Ok(results) => {
+                    let (answer, best_time) = results
+                        .into_iter()
+                        .min_by_key(|(_ans, duration)| *duration)
+                        .unwrap();
+                    println!(
+                        "Plan {}: {:?} {} {}",
+                        num,
+                        best_time,
+                        answer,
+                        write_plan(&plan)
+                    );
+                    if options.analyze {
+                        print_analysis(graph, &plan, options, "saved");
+                    }
+                }
+                Err(msg) => println!("unable to execute plan {}: {}", num, msg),
+            }
+            if let Some(keep) = options.max_labels {
+                graph.evict(keep);
+            }
+        });
+}
+
+/// Write Graphviz files of the best and chosen plans and of the e-graph.
+fn write_dots(
+    dir: &Path,
+    num: &str,
+    runner: &Runner<Plan, ()>,
+    best_plan: &RecExpr<Plan>,
+    chosen_plan: &RecExpr<Plan>,
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join(format!("{}-best.dot", num)),
+        dot::plan_dot(best_plan),
+    )?;
+    std::fs::write(
+        dir.join(format!("{}-chosen.dot", num)),
+        dot::plan_dot(chosen_plan),
+    )?;
+    std::fs::write(
+        dir.join(format!("{}-egraph.dot", num)),
+        dot::egraph_dot(&runner.egraph, chosen_plan),
+    )
+}
+
+fn saturate(exprs: &[&RecExpr<Plan>], hints: &[Hint], options: &Options) -> Runner<Plan, ()> {
+    let mut runner = if options.explain {
+        Runner::default().with_explanations_enabled()
+    } else {
+        Runner::default().with_explanations_disabled()
+    };
+    if let Some(limit) = options.iter_limit {
+        runner = runner.with_iter_limit(limit);
+    }
+    if let Some(limit) = options.node_limit {
+        runner = runner.with_node_limit(limit);
+    }
+    if let Some(limit) = options.time_limit {
+        runner = runner.with_time_limit(limit);
+    }
+    for expr in exprs {
+        runner = runner.with_expr(expr);
+    }
+    let rules: Vec<_> = make_rules()
+        .into_iter()
+        .filter(|rule| {
+            !(hints.contains(&Hint::NoDistribute) && rule.name.as_str().starts_with("distribute"))
+        })
+        .collect();
+    runner.run(&rules)
+}
+
+fn print_saturation(runner: &Runner<Plan, ()>) {
+    let report = runner.report();
+    println!(
+        "    Saturation: {:?} after {} iterations, {} e-nodes, {} e-classes, {:.3}s",
+        report.stop_reason,
+        report.iterations,
+        report.egraph_nodes,
+        report.egraph_classes,
+        report.total_time
+    );
+    let mut applied: BTreeMap<String, usize> = BTreeMap::new();
+    for iteration in runner.iterations.iter() {
+        for (rule, count) in iteration.applied.iter() {
+            *applied.entry(rule.to_string()).or_default() += count;
+        }
+    }
+    for (rule, count) in applied {
+        println!("        {}: {}", rule, count);
+    }
+}
+
+/// Print the sequence of rewrites turning the query expression into the plan.
+fn print_explanation(
+    runner: &mut Runner<Plan, ()>,
+    expr: &RecExpr<Plan>,
+    plan: &RecExpr<Plan>,
+    name: &str,
+) {
+    println!("    Explanation of {} plan:", name);
+    runner
+        .explain_equivalence(expr, plan)
+        .get_flat_strings()
+        .iter()
+        .for_each(|step| println!("        {}", step));
+}
+
+/// Evaluate the plan with the engine of the benchmark and print its profile.
+fn print_analysis(graph: &Graph, plan: &RecExpr<Plan>, options: &Options, name: &str) {
+    let profile = if options.parallel.is_some() || node_by_node(options) {
+        eval_profiled(graph, plan.clone(), &limits(options))
+    } else {
+        eval_profiled_rpq(graph, plan.clone())
+    };
+    match profile {
+        Ok((_, profile)) => {
+            println!("    Profile of {} plan:", name);
+            profile
+                .to_string()
+                .lines()
+                .for_each(|line| println!("        {}", line));
+        }
+        Err(err) => println!("    Unable to profile {} plan: {}", name, err),
+    }
+}
+
+/// Report a plan skipped because of the limits instead of silently dropping it.
+fn report_skipped(plan: &RecExpr<Plan>, err: &EvalError) {
+    if let EvalError::TimedOut | EvalError::OverBudget { .. } = err {
+        println!("    Skipped {} ({})", plan, err);
+    }
+}
+
 fn run_random<'a>(
     graph: &'a Graph,
     runs: u32,
-    expr: &'a RecExpr<Plan>,
+    options: &'a Options,
+    runner: &'a Runner<Plan, ()>,
 ) -> impl Iterator<Item = (RecExpr<Plan>, usize, Duration)> + 'a {
-    let rules = make_rules();
-
-    let runner = Runner::default()
-        .with_explanations_disabled()
-        .with_expr(expr)
-        .run(&rules);
-
     // This should perform a heat up.
     (0..runs).for_each(|_| {
         let extractor = egg::Extractor::new(&runner.egraph, RandomCostFn);
         let (_, plan) = extractor.find_best(runner.roots[0]);
-        let _ = eval(graph, plan);
+        let _ = evaluate(graph, plan, options);
     });
 
     (0..runs).filter_map(move |_| {
@@ -57,80 +329,510 @@ fn run_random<'a>(
         let (_, plan) = extractor.find_best(runner.roots[0]);
         let start = std::time::Instant::now();
         // TODO: check answers.
-        let answer = eval(graph, plan.clone()).ok()?;
+        let answer = evaluate(graph, plan.clone(), options)
+            .map_err(|err| report_skipped(&plan, &err))
+            .ok()?;
         Some((plan, answer, start.elapsed()))
     })
 }
 
-fn main() {
-    unsafe {
-        let res = LAGraph_Init(std::ptr::null_mut());
-        assert_eq!(res, 0);
+/// Evaluate every plan `runs` times keeping the best time of each.
+fn run_plans<'a>(
+    graph: &'a Graph,
+    runs: u32,
+    options: &'a Options,
+    plans: Vec<RecExpr<Plan>>,
+) -> impl Iterator<Item = (RecExpr<Plan>, usize, Duration)> + 'a {
+    plans.into_iter().filter_map(move |plan| {
+        let (answer, duration) = (0..runs.max(1))
+            .map(|_| {
+                let start = std::time::Instant::now();
+                let answer = evaluate(graph, plan.clone(), options)
+                    .map_err(|err| report_skipped(&plan, &err))
+                    .ok()?;
+                Some((answer, start.elapsed()))
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min_by_key(|(_ans, duration)| *duration)?;
+        Some((plan, answer, duration))
+    })
+}
+
+/// Evaluate the plan picked by [`EstimateCostFn`] following the hints.
+///
+/// With the `from-dest` hint the second root of the runner must be the
+/// transposed query.
+fn run_chosen(
+    graph: &Graph,
+    runner: &Runner<Plan, ()>,
+    hints: &[Hint],
+    options: &Options,
+) -> (RecExpr<Plan>, Result<usize, EvalError>, Duration) {
+    let cost_fn = EstimateCostFn {
+        nverts: graph.nverts(),
+        workers: options.parallel.unwrap_or(1),
+        stats: graph.stats(),
+    };
+    let is_forward = |plan: &Plan| !matches!(plan, Plan::Transpose(_));
+    let hinted = if hints.contains(&Hint::FromDest) {
+        extract_rooted(&runner.egraph, runner.roots[1], cost_fn.clone(), is_forward).map(
+            |mut plan| {
+                plan.add(Plan::Transpose([Id::from(plan.as_ref().len() - 1)]));
+                plan
+            },
+        )
+    } else if hints.contains(&Hint::FromSrc) {
+        extract_rooted(&runner.egraph, runner.roots[0], cost_fn.clone(), is_forward)
+    } else {
+        None
+    };
+    let plan = hinted.unwrap_or_else(|| {
+        if hints.contains(&Hint::FromDest) || hints.contains(&Hint::FromSrc) {
+            println!("    No plan follows the direction hint, choosing any");
+        }
+        egg::Extractor::new(&runner.egraph, cost_fn)
+            .find_best(runner.roots[0])
+            .1
+    });
+    let start = std::time::Instant::now();
+    let answer = evaluate(graph, plan.clone(), options);
+    (plan, answer, start.elapsed())
+}
+
+/// Evaluate the plan given in the query file after checking it is equivalent
+/// to the query.
+fn run_hinted(graph: &Graph, options: &Options, expr: &RecExpr<Plan>, plan: &str) {
+    let plan = match parse_plan(plan).and_then(|plan| graph.resolve(&plan)) {
+        Ok(plan) => plan,
+        Err(msg) => {
+            println!("    Invalid plan hint: {}", msg);
+            return;
+        }
+    };
+    let runner = saturate(&[expr, &plan], &[], options);
+    if runner.egraph.find(runner.roots[0]) != runner.egraph.find(runner.roots[1]) {
+        println!(
+            "    Hinted plan {} is not proven equivalent to the query ({:?})",
+            write_plan(&plan),
+            runner.stop_reason.unwrap()
+        );
+        return;
     }
+    match run_plans(
+        graph,
+        options.runs.unwrap_or(1),
+        options,
+        vec![plan.clone()],
+    )
+    .next()
+    {
+        Some((plan, ans, time)) => println!("    Hinted {:?}: {} {}", time, write_plan(&plan), ans),
+        None => println!("    Hinted plan {} failed", write_plan(&plan)),
+    }
+}
 
-    let graph_path = std::env::args().nth(1).unwrap();
-    let graph_path = Path::new(&graph_path);
-    let graph = graph::load_dir(graph_path).expect("unable to load graph");
+/// Print paths matching the query between the hinted pairs of vertices and
+/// between a sample of answers of the plan.
+fn print_witnesses(
+    graph: &Graph,
+    options: &Options,
+    query: &Query,
+    plan: &RecExpr<Plan>,
+    hints: &[Hint],
+) {
+    let is_other = |vert: &Vertex, name: &str| matches!(vert, Vertex::Con(con) if con != name);
+    let mut pairs = vec![];
+    for hint in hints {
+        if let Hint::Witness(src, dest) = hint {
+            match (graph.verts.get(src), graph.verts.get(dest)) {
+                _ if is_other(&query.src, src) || is_other(&query.dest, dest) => {
+                    println!("    <{}> to <{}> is not an answer", src, dest)
+                }
+                (Some(&src), Some(&dest)) => pairs.push((src as grb::Index, dest as grb::Index)),
+                (None, _) => println!("    Witness of no such vertex: {}", src),
+                (_, None) => println!("    Witness of no such vertex: {}", dest),
+            }
+        }
+    }
+    if let Some(count) = options.witness.filter(|&count| count > 0) {
+        let answers = eval_semiring(graph, plan.clone(), Semiring::Boolean, &limits(options))
+            .and_then(|res| Ok(res.pairs()?));
+        match answers {
+            Ok(answers) => {
+                let step = (answers.len() / count).max(1);
+                pairs.extend(answers.into_iter().step_by(step).take(count));
+            }
+            Err(err) => println!("    Sampling answers failed: {}", err),
+        }
+    }
+    let nfa = query.pattern.nfa();
+    for (src, dest) in pairs {
+        let (src_name, dest_name) = (graph.vertex_name(src), graph.vertex_name(dest));
+        match find_witness(graph, &nfa, src, dest, &limits(options)) {
+            Ok(Some(witness)) => println!("    Witness: {}", witness),
+            Ok(None) => println!("    <{}> to <{}> is not an answer", src_name, dest_name),
+            Err(err) => println!(
+                "    Witness of <{}> to <{}> failed: {}",
+                src_name, dest_name, err
+            ),
+        }
+    }
+}
+
+fn bench_query(
+    graph: &Graph,
+    options: &Options,
+    num: &str,
+    query: &Query,
+    expr: &RecExpr<Plan>,
+    hints: &[Hint],
+) -> Option<RecExpr<Plan>> {
+    let mut transposed = expr.clone();
+    transposed.add(Plan::Transpose([Id::from(expr.as_ref().len() - 1)]));
+    let mut roots = vec![expr];
+    if hints.contains(&Hint::FromDest) {
+        roots.push(&transposed);
+    }
+    let mut runner = saturate(&roots, hints, options);
+    let results: Vec<(RecExpr<Plan>, usize, Duration)> = match options.exhaustive {
+        Some(cap) => {
+            let plans = enumerate_plans(&runner.egraph, runner.roots[0], cap);
+            println!(
+                "Plan space for {:?}: {} plans, {} enumerated",
+                query,
+                count_plans(&runner.egraph, runner.roots[0]),
+                plans.len()
+            );
+            run_plans(graph, options.runs.unwrap_or(1), options, plans).collect()
+        }
+        None => run_random(graph, options.runs.unwrap_or(1000), options, &runner).collect(),
+    };
+    if results.is_empty() {
+        println!("unable to evaluate any plan for {:?}", query);
+        return None;
+    }
+    let runs = results.len() as u32;
+    // Round up so a handful of exhaustive runs still lists one.
+    let first_n_runs = (runs + 99) / 100;
+    println!("Stats for {:?}", query);
+    if let Ok(nthreads) = threads() {
+        println!("    Threads: {}", nthreads);
+    }
+    println!("    Engine: {}", engine(options));
+    println!("    First {:?} runs", first_n_runs);
+    // let mut flag = true;
+    results
+        .iter()
+        .take(first_n_runs.try_into().unwrap())
+        .for_each(|(plan, ans, duration)| {
+            println!("    - {:?} {} {}", duration, plan, ans);
+            // if flag == true {
+            //     res = *ans;
+            //     flag = false;
+            // }
+        });
+    // flag = true;
+
+    // results.sort_by_key(|(_plan, _ans, duration)| duration);
+    let (best_plan, _, best_time) = results
+        .iter()
+        .min_by_key(|(_plan, _ans, duration)| duration)
+        .unwrap();
+    let (worst_plan, _, worst_time) = results
+        .iter()
+        .max_by_key(|(_plan, _ans, duration)| duration)
+        .unwrap();
+    let mean_time = results
+        .iter()
+        .map(|(_plan, _ans, duration)| duration)
+        .sum::<Duration>()
+        .div(runs);
+    let (_, _, median_time) = results[results.len() / 2].clone();
+
+    println!("    Best {:?}: {}", best_time, best_plan);
+    println!("    Worst {:?}: {}", worst_time, worst_plan);
+    println!("    Mean: {:?}", mean_time);
+    println!("    Median: {:?}", median_time);
+
+    let (chosen_plan, chosen_answer, chosen_time) = run_chosen(graph, &runner, hints, options);
+    match chosen_answer {
+        Ok(_) => println!("    Chosen {:?}: {}", chosen_time, chosen_plan),
+        Err(msg) => println!("    Chosen {} failed: {}", chosen_plan, msg),
+    }
+    if let Some(semiring) = options.semiring {
+        let summary = eval_semiring(graph, chosen_plan.clone(), semiring, &limits(options))
+            .and_then(|res| Ok(semiring.summary(&res)?));
+        match summary {
+            Ok(summary) => println!("    Semiring {}: {}", semiring, summary),
+            Err(err) => println!("    Semiring {} failed: {}", semiring, err),
+        }
+    }
+    print_witnesses(graph, options, query, &chosen_plan, hints);
+    print_saturation(&runner);
+
+    if options.explain {
+        print_explanation(&mut runner, expr, best_plan, "best");
+        print_explanation(&mut runner, expr, &chosen_plan, "chosen");
+    }
+
+    if options.analyze {
+        print_analysis(graph, best_plan, options, "best");
+        print_analysis(graph, &chosen_plan, options, "chosen");
+    }
+
+    if let Some(dir) = &options.dot {
+        if let Err(err) = write_dots(dir, num, &runner, best_plan, &chosen_plan) {
+            println!("unable to write plan graphs: {}", err);
+        }
+    }
+
+    println!();
+    // println!("{};{};{:?}", i, res, best_time.as_nanos());
+    // i = i + 1;
+    Some(best_plan.clone())
+}
 
-    let queries_path = std::env::args().nth(2).unwrap();
-    let queries_path = Path::new(&queries_path);
+/// Benchmark every query of the file, saving the best plans into `saved_plans`.
+fn run_queries(graph: &Graph, options: &Options, saved_plans: &mut Option<std::fs::File>) {
+    let interrupt = INTERRUPT.get_or_init(Cancel::default);
     // let mut i = 1;
     // let mut res = 9999999999;
-    read_queries(queries_path).into_iter().for_each(|query| {
-        println!("Running {:?}", query);
-        let expr = graph.run(query.clone());
-
-        match expr {
-            Ok(expr) => {
-                let runs: u32 = 1000;
-                let results: Vec<(RecExpr<Plan>, usize, Duration)> =
-                    run_random(&graph, runs, &expr).collect();
-                let first_n_runs = runs / 100;
-                println!("Stats for {:?}", query);
-                println!("    First {:?} runs", first_n_runs);
-                // let mut flag = true;
-                results
-                    .iter()
-                    .take(first_n_runs.try_into().unwrap())
-                    .for_each(|(plan, ans, duration)| {
-                        println!("    - {:?} {} {}", duration, plan, ans);
-                        // if flag == true {
-                        //     res = *ans;
-                        //     flag = false;
-                        // }
-                    });
-                // flag = true;
-
-                // results.sort_by_key(|(_plan, _ans, duration)| duration);
-                let (best_plan, _, best_time) = results
-                    .iter()
-                    .min_by_key(|(_plan, _ans, duration)| duration)
-                    .unwrap();
-                let (worst_plan, _, worst_time) = results
-                    .iter()
-                    .max_by_key(|(_plan, _ans, duration)| duration)
-                    .unwrap();
-                let mean_time = results
-                    .iter()
-                    .map(|(_plan, _ans, duration)| duration)
-                    .sum::<Duration>()
-                    .div(runs);
-                let (_, _, median_time) = results[results.len() / 2].clone();
-
-                println!("    Best {:?}: {}", best_time, best_plan);
-                println!("    Worst {:?}: {}", worst_time, worst_plan);
-                println!("    Mean: {:?}", mean_time);
-                println!("    Median: {:?}", median_time);
-
+    read_queries(&options.queries)
+        .into_iter()
+        .for_each(|(num, query, hints)| {
+            if interrupt.check().is_err() {
+                return;
+            }
+            println!("Running {:?}", query);
+            let hints = match hints {
+                Ok(hints) => hints,
+                Err(msg) => {
+                    println!("invalid hints: {}", msg);
+                    return;
+                }
+            };
+            let expr = match graph.run(query.clone()) {
+                Ok(expr) => expr,
+                Err(msg) => {
+                    println!("unable to execute query: {}", msg);
+                    return;
+                }
+            };
+            let restore = hints.iter().find_map(|hint| match hint {
+                &Hint::Threads(nthreads) => {
+                    let current = threads().ok()?;
+                    use_threads(nthreads, options)
+                        .map_err(|msg| println!("unable to set threads: {}", msg))
+                        .ok()?;
+                    Some(current)
+                }
+                _ => None,
+            });
+            if let Some(Hint::Plan(plan)) = hints.first() {
+                run_hinted(graph, options, &expr, plan);
                 println!();
-                // println!("{};{};{:?}", i, res, best_time.as_nanos());
-                // i = i + 1;
+            } else {
+                let best_plan = bench_query(graph, options, &num, &query, &expr, &hints);
+                if let (Some(file), Some(plan)) = (saved_plans.as_mut(), best_plan) {
+                    writeln!(file, "{},{}", num, write_plan(&plan)).expect("unable to save plan");
+                }
             }
-            Err(msg) => {
-                println!("unable to execute query: {}", msg);
+            if let Some(nthreads) = restore {
+                use_threads(nthreads, options).expect("unable to restore threads");
+            }
+            if let Some(keep) = options.max_labels {
+                graph.evict(keep);
+            }
+        });
+}
+
+/// Register every query of the file as a standing query.
+fn standing_queries(graph: &Graph, options: &Options) -> Vec<StandingQuery> {
+    read_queries(&options.queries)
+        .into_iter()
+        .filter_map(|(num, query, _)| {
+            let registered = graph
+                .run(query.clone())
+                .map_err(EvalError::from)
+                .and_then(|expr| StandingQuery::new(graph, num.clone(), &expr, &limits(options)));
+            match registered.and_then(|standing| Ok((standing.result().nvals()?, standing))) {
+                Ok((answers, standing)) => {
+                    println!("Standing query {} {:?}: {} answers", num, query, answers);
+                    Some(standing)
+                }
+                Err(err) => {
+                    println!("unable to register query {}: {}", num, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Print up to 10 answer pairs gained or lost by a standing query.
+fn print_changes(graph: &Graph, sign: char, pairs: &[(grb::Index, grb::Index)]) {
+    for &(src, dest) in pairs.iter().take(10) {
+        println!(
+            "        {} <{}> <{}>",
+            sign,
+            graph.vertex_name(src),
+            graph.vertex_name(dest)
+        );
+    }
+    if pairs.len() > 10 {
+        println!("        {} {} more", sign, pairs.len() - 10);
+    }
+}
+
+/// Apply the updates of the file to the graph, maintaining the standing
+/// queries, and write the graph back with `--write-back`.
+fn apply_updates(
+    graph: &mut Graph,
+    file: &Path,
+    options: &Options,
+    standing: &mut [StandingQuery],
+) -> Result<(), String> {
+    let updates = update::read_updates(file).map_err(|err| err.to_string())?;
+    let mut changed = 0;
+    for update in &updates {
+        if !update.apply(graph)? {
+            continue;
+        }
+        changed += 1;
+        if !standing.is_empty() {
+            println!("{}", update);
+        }
+        for query in standing.iter_mut() {
+            match query.update(graph, update, &limits(options)) {
+                Ok(changes) if changes.added.is_empty() && changes.removed.is_empty() => {}
+                Ok(changes) => {
+                    println!(
+                        "    Query {}: {} added, {} removed",
+                        query.name,
+                        changes.added.len(),
+                        changes.removed.len()
+                    );
+                    print_changes(graph, '+', &changes.added);
+                    print_changes(graph, '-', &changes.removed);
+                }
+                Err(err) => println!("    Query {} failed: {}", query.name, err),
             }
         }
+    }
+    println!(
+        "Applied {} of {} updates, the others changed nothing\n",
+        changed,
+        updates.len()
+    );
+    if options.write_back {
+        let path = &options.graph;
+        // Kept statistics are computed again for the written graph.
+        let had_stats = stats::stats_file(path).exists();
+        let written = if path.extension().map_or(false, |ext| ext == "snap") {
+            snapshot::save_snapshot(graph, path)
+        } else if path.is_dir() && !path.join("nodes.csv").exists() {
+            graph.write_back(path)
+        } else {
+            return Err("only dataset directories and snapshots can be written back".to_string());
+        };
+        written.map_err(|err| err.to_string())?;
+        if had_stats {
+            graph.set_stats(None);
+            let stats = stats::load_or_compute(graph, path, options.max_labels, false)?;
+            graph.set_stats(Some(stats));
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    unsafe {
+        let res = LAGraph_Init(std::ptr::null_mut());
+        assert_eq!(res, 0);
+    }
+
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|msg| {
+        eprintln!("{}\n\n{}", msg, USAGE);
+        std::process::exit(2);
+    });
+
+    let mut graph = graph::load(&options.graph, &options.columns).unwrap_or_else(|err| {
+        eprintln!("unable to load graph: {}", err);
+        std::process::exit(1);
     });
+    // Snapshots hold their statistics already.
+    if let Some(stats) = stats::load_current(&options.graph) {
+        graph.set_stats(Some(Arc::new(stats)));
+    }
+
+    if let Some(file) = &options.updates {
+        let mut standing = match options.standing {
+            true => standing_queries(&graph, &options),
+            false => vec![],
+        };
+        if let Err(err) = apply_updates(&mut graph, file, &options, &mut standing) {
+            eprintln!("unable to update graph: {}", err);
+            std::process::exit(1);
+        }
+        if options.standing {
+            return;
+        }
+    }
+
+    if let Some(dir) = &options.convert {
+        let saved = if dir.extension().map_or(false, |ext| ext == "snap") {
+            snapshot::save_snapshot(&graph, dir)
+        } else {
+            graph::save_dir(&graph, dir)
+        };
+        if let Err(err) = saved {
+            eprintln!("unable to write graph: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if options.stats {
+        let changed = options.updates.is_some() && !options.write_back;
+        match stats::load_or_compute(&graph, &options.graph, options.max_labels, changed) {
+            Ok(stats) => print!("{}", stats),
+            Err(err) => {
+                eprintln!("unable to compute statistics: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    INTERRUPT.get_or_init(Cancel::default);
+    unsafe {
+        libc::signal(libc::SIGINT, on_interrupt as libc::sighandler_t);
+    }
+
+    let mut saved_plans = options
+        .save_plans
+        .as_ref()
+        .map(|file| std::fs::File::create(file).expect("unable to create plans file"));
+
+    // Without --threads run once with the default number of threads.
+    let thread_counts = match options.threads.as_slice() {
+        [] => vec![threads().expect("unable to get the number of threads")],
+        counts => counts.to_vec(),
+    };
+    for nthreads in thread_counts {
+        use_threads(nthreads, &options).expect("unable to set threads");
+        if options.threads.len() > 1 {
+            println!("Threads: {}\n", nthreads);
+        }
+        if options.replay {
+            replay(&graph, &options);
+        } else {
+            // Only the first thread count saves its plans, so every query
+            // is saved once.
+            run_queries(&graph, &options, &mut saved_plans.take());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{graph::Graph, grb};
+
+/// Split the next term off a triple line. IRIs lose their angle brackets,
+/// blank nodes and literals (with their language tag or datatype) are kept
+/// as written.
+fn term(line: &str) -> Result<(&str, &str), String> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('<') {
+        let end = rest.find('>').ok_or("unterminated IRI")?;
+        return Ok((&rest[..end], &rest[end + 1..]));
+    }
+    let end = if let Some(literal) = line.strip_prefix('"') {
+        let mut escaped = false;
+        let close = literal
+            .find(|c| {
+                let found = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                found
+            })
+            .ok_or("unterminated literal")?
+            + 2;
+        match &line[close..] {
+            rest if rest.starts_with("^^<") => {
+                close + rest.find('>').ok_or("unterminated datatype IRI")? + 1
+            }
+            // Language tags are letters, digits and dashes.
+            rest if rest.starts_with('@') => {
+                let tag = rest[1..]
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                    .unwrap_or(rest.len() - 1);
+                close + 1 + tag
+            }
+            _ => close,
+        }
+    } else if line.starts_with("_:") {
+        // Blank node labels may contain dots but do not end with one.
+        let label = &line[..line.find(char::is_whitespace).unwrap_or(line.len())];
+        label.trim_end_matches('.').len()
+    } else {
+        return Err(format!("unexpected term: {}", line));
+    };
+    Ok(line.split_at(end))
+}
+
+/// Parse a triple line into its subject, predicate and object, or nothing for
+/// blank and comment lines.
+fn parse_triple(line: &str) -> Result<Option<(&str, &str, &str)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (subj, rest) = term(line)?;
+    let (pred, rest) = term(rest)?;
+    if pred.starts_with('"') || pred.starts_with("_:") {
+        return Err(format!("predicate is not an IRI: {}", pred));
+    }
+    let (obj, rest) = term(rest)?;
+    match rest.trim() {
+        "." => Ok(Some((subj, pred, obj))),
+        "" => Err("missing '.'".to_string()),
+        rest => Err(format!("expected '.' instead of: {}", rest)),
+    }
+}
+
+/// Load a graph from an N-Triples file, with one vertex per distinct subject
+/// and object and one label per predicate.
+pub fn load_ntriples(path: &Path) -> io::Result<Graph> {
+    let file = BufReader::new(std::fs::File::open(path)?);
+    let mut verts: HashMap<String, usize> = HashMap::new();
+    let mut edges: HashMap<String, Vec<(grb::Index, grb::Index)>> = HashMap::new();
+    let mut vertex = |name: &str| {
+        let next = verts.len();
+        *verts.entry(name.to_string()).or_insert(next) as grb::Index
+    };
+    for (num, line) in file.lines().enumerate() {
+        let line = line?;
+        let triple = parse_triple(&line).map_err(|msg| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), num + 1, msg),
+            )
+        })?;
+        if let Some((subj, pred, obj)) = triple {
+            let edge = (vertex(subj), vertex(obj));
+            edges.entry(pred.to_string()).or_default().push(edge);
+        }
+    }
+    Graph::new(verts, edges).map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_triple() {
+        assert_eq!(
+            parse_triple("<a> <knows> <b> ."),
+            Ok(Some(("a", "knows", "b")))
+        );
+        assert_eq!(
+            parse_triple(r#"_:x <name> "A \"b\" c"@en ."#),
+            Ok(Some(("_:x", "name", r#""A \"b\" c"@en"#)))
+        );
+        assert_eq!(
+            parse_triple("<a> <age> \"3\"^^<http://www.w3.org/2001/XMLSchema#int>."),
+            Ok(Some((
+                "a",
+                "age",
+                "\"3\"^^<http://www.w3.org/2001/XMLSchema#int>"
+            )))
+        );
+        assert_eq!(
+            parse_triple(r#"<s> <p> "c"@en-GB."#),
+            Ok(Some(("s", "p", r#""c"@en-GB"#)))
+        );
+        assert_eq!(
+            parse_triple("<s> <p> _:b1.x."),
+            Ok(Some(("s", "p", "_:b1.x")))
+        );
+        assert_eq!(
+            parse_triple("_:b1 <p> _:b2 ."),
+            Ok(Some(("_:b1", "p", "_:b2")))
+        );
+        assert_eq!(parse_triple("  # comment"), Ok(None));
+        assert_eq!(
+            parse_triple("<a> <b> <c"),
+            Err("unterminated IRI".to_string())
+        );
+        assert_eq!(parse_triple("<a> <b> <c>"), Err("missing '.'".to_string()));
+    }
+
+    #[test]
+    fn test_load_ntriples() {
+        crate::eval::init_test();
+        let path = std::env::temp_dir().join(format!("rpq-{}.nt", std::process::id()));
+        std::fs::write(
+            &path,
+            "<a> <knows> <b> .\n# comment\n<b> <knows> _:c .\n_:c <name> \"C\"@en .\n",
+        )
+        .unwrap();
+        let graph = load_ntriples(&path).unwrap();
+        assert_eq!(graph.nverts(), 4);
+        assert_eq!(graph.labels(), vec!["knows", "name"]);
+        assert_eq!(graph.edges("knows"), vec![("a", "b"), ("b", "_:c")]);
+        assert_eq!(graph.edges("name"), vec![("_:c", "\"C\"@en")]);
+
+        std::fs::write(&path, "<a> <knows> <b> .\n<a> \"knows\" <b> .\n").unwrap();
+        assert!(load_ntriples(&path)
+            .err()
+            .unwrap()
+            .to_string()
+            .ends_with(":2: predicate is not an IRI: \"knows\""));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use crate::{csv::Columns, semiring::Semiring};
+
+pub const USAGE: &str = "\
+usage: la-n-egg-rpq <graph> <queries file> [options]
+       la-n-egg-rpq <graph> <plans file> --replay [--runs <n>] [--analyze]
+       la-n-egg-rpq convert <graph> <dir or snapshot> [--columns <c=n,..>]
+       la-n-egg-rpq stats <graph> [--columns <c=n,..>]
+
+<graph> is a directory with edges.txt, vertices.txt and MatrixMarket files of
+the labels (vertex ids are 1-based matrix rows, or 0-based when vertices.txt
+starts with a `% base 0` line), a directory with nodes.csv and edges.csv tables of a property
+graph, an N-Triples file ending with .nt, a binary snapshot ending with .snap,
+or a file with a `<src> <label> <dest>` edge on every line. convert writes the
+graph into a snapshot when the output ends with .snap, otherwise into a
+directory of the first kind. stats prints degree statistics of the labels and
+keeps them in stats.tsv inside a graph directory or in <graph>.stats, where
+the planner reads them until the files of the graph change. Snapshots hold
+the statistics of their labels.
+
+options:
+    --columns <c=n,..>  names of the id, labels, src, dst and type columns of the
+                        CSV tables, e.g. id=node_id,type=rel
+    --max-labels <n>    keep at most <n> label matrices read from a dataset
+                        directory in memory between queries, freeing the
+                        least recently used ones
+    --updates <file>    apply `+ <src> <label> <dest>` edge insertions and
+                        `- <src> <label> <dest>` deletions, one per line,
+                        before running the queries
+    --write-back        write the updated graph back into its dataset
+                        directory or snapshot
+    --standing          keep the answers of the queries up to date while
+                        applying --updates and print the changed ones instead
+                        of benchmarking the queries
+    --runs <n>          evaluations of each plan (1000 random plans, or 1 per
+                        plan with --exhaustive)
+    --exhaustive <cap>  evaluate every distinct plan, at most <cap> of them
+    --iter-limit <n>    equality saturation iteration limit
+    --node-limit <n>    equality saturation e-node limit
+    --time-limit <s>    equality saturation time limit in seconds
+    --timeout <s>       stop evaluations of a plan after <s> seconds; plans
+                        are then evaluated node by node instead of with a
+                        single LAGraph_RPQMatrix call, so timings are not
+                        comparable with runs without it (the engine is
+                        printed with the stats of every query)
+    --memory-budget <m> skip plans whose intermediate results need (or are
+                        estimated to need) more than <m> MiB; evaluates plans
+                        node by node like --timeout
+    --semiring <s>      evaluate the chosen plan once more over `counting`
+                        (number of paths, once per way an ambiguous pattern
+                        like <a>|<a> matches them; `counting:<k>` repeats
+                        closures at most <k> times), `tropical` (shortest
+                        path lengths) or `boolean` semiring and summarize
+                        the result
+    --witness <n>       print a path matching the pattern for <n> answers of
+                        the chosen plan; a `witness=<src>,<dest>` hint asks
+                        for a path between the given vertices
+    --explain           print rewrites turning the query into the best and
+                        the chosen plans
+    --threads <n,..>    run all queries once with every given number of
+                        GraphBLAS threads, e.g. 1,2,4,8; a `threads=<n>`
+                        hint overrides it for a single query
+    --parallel <n>      evaluate independent sub-plans concurrently on <n>
+                        workers sharing the GraphBLAS threads, node by node
+                        like --timeout; the chosen plan accounts for them
+    --analyze           evaluate the best and the chosen plans once more and
+                        print the result size of every plan node computed by
+                        LAGraph_RPQMatrix, or with node by node evaluation
+                        the time, result size and fixpoint iterations of
+                        every plan node
+    --dot <dir>         write Graphviz files of the best and chosen plans and
+                        of the e-graph for every query into <dir>
+    --save-plans <file> write the best plan of every query into <file>
+    --replay            evaluate plans saved with --save-plans";
+
+pub struct Options {
+    pub graph: PathBuf,
+    pub columns: Columns,
+    pub max_labels: Option<usize>,
+    pub updates: Option<PathBuf>,
+    pub write_back: bool,
+    pub standing: bool,
+    pub queries: PathBuf,
+    pub runs: Option<u32>,
+    pub exhaustive: Option<usize>,
+    pub iter_limit: Option<usize>,
+    pub node_limit: Option<usize>,
+    pub time_limit: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub memory_budget: Option<u64>,
+    pub parallel: Option<usize>,
+    pub threads: Vec<i32>,
+    pub semiring: Option<Semiring>,
+    pub witness: Option<usize>,
+    pub explain: bool,
+    pub analyze: bool,
+    pub dot: Option<PathBuf>,
+    pub save_plans: Option<PathBuf>,
+    pub replay: bool,
+    pub convert: Option<PathBuf>,
+    pub stats: bool,
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+/// Parse a non-negative, finite number of seconds.
+fn parse_secs(flag: &str, value: Option<String>) -> Result<Duration, String> {
+    let secs: f64 = parse_value(flag, value)?;
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid value for {}: {}", flag, secs))
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut graph: PathBuf = args.next().ok_or("missing graph")?.into();
+        let mut convert = None;
+        let mut stats = false;
+        if graph.as_os_str() == "convert" {
+            graph = args.next().ok_or("missing graph")?.into();
+            convert = Some(args.next().ok_or("missing output dir")?.into());
+        } else if graph.as_os_str() == "stats" {
+            graph = args.next().ok_or("missing graph")?.into();
+            stats = true;
+        }
+        let queries = match convert.is_some() || stats {
+            true => PathBuf::new(),
+            false => args.next().ok_or("missing queries file")?.into(),
+        };
+        let mut options = Options {
+            graph,
+            columns: Columns::default(),
+            max_labels: None,
+            updates: None,
+            write_back: false,
+            standing: false,
+            queries,
+            runs: None,
+            exhaustive: None,
+            iter_limit: None,
+            node_limit: None,
+            time_limit: None,
+            timeout: None,
+            memory_budget: None,
+            parallel: None,
+            threads: vec![],
+            semiring: None,
+            witness: None,
+            explain: false,
+            analyze: false,
+            dot: None,
+            save_plans: None,
+            replay: false,
+            convert,
+            stats,
+        };
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--columns" => options.columns = parse_value(&flag, args.next())?,
+                "--max-labels" => options.max_labels = Some(parse_value(&flag, args.next())?),
+                "--updates" => options.updates = Some(parse_value(&flag, args.next())?),
+                "--write-back" => options.write_back = true,
+                "--standing" => options.standing = true,
+                "--runs" => options.runs = Some(parse_value(&flag, args.next())?),
+                "--exhaustive" => options.exhaustive = Some(parse_value(&flag, args.next())?),
+                "--iter-limit" => options.iter_limit = Some(parse_value(&flag, args.next())?),
+                "--node-limit" => options.node_limit = Some(parse_value(&flag, args.next())?),
+                "--time-limit" => options.time_limit = Some(parse_secs(&flag, args.next())?),
+                "--timeout" => options.timeout = Some(parse_secs(&flag, args.next())?),
+                "--memory-budget" => {
+                    let mib: u64 = parse_value(&flag, args.next())?;
+                    let bytes = mib
+                        .checked_mul(1 << 20)
+                        .ok_or(format!("invalid value for {}: {} is too large", flag, mib))?;
+                    options.memory_budget = Some(bytes);
+                }
+                "--parallel" => options.parallel = Some(parse_value(&flag, args.next())?),
+                "--threads" => {
+                    let counts: String = parse_value(&flag, args.next())?;
+                    options.threads = counts
+                        .split(',')
+                        .map(|count| match count.trim().parse() {
+                            Ok(count) if count > 0 => Ok(count),
+                            _ => Err(format!("invalid value for {}: {}", flag, counts)),
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "--semiring" => options.semiring = Some(parse_value(&flag, args.next())?),
+                "--witness" => options.witness = Some(parse_value(&flag, args.next())?),
+                "--explain" => options.explain = true,
+                "--analyze" => options.analyze = true,
+                "--dot" => options.dot = Some(parse_value(&flag, args.next())?),
+                "--save-plans" => options.save_plans = Some(parse_value(&flag, args.next())?),
+                "--replay" => options.replay = true,
+                _ => return Err(format!("unknown option: {}", flag)),
+            }
+        }
+        if (options.write_back || options.standing) && options.updates.is_none() {
+            return Err("--write-back and --standing need --updates".to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let options = parse("graph queries --time-limit 1.5").unwrap();
+        assert_eq!(options.time_limit, Some(Duration::from_millis(1500)));
+        assert_eq!(
+            parse("graph queries --time-limit -1").err(),
+            Some("invalid value for --time-limit: -1".to_string())
+        );
+        assert!(parse("graph queries --time-limit inf").is_err());
+        assert!(parse("graph queries --time-limit NaN").is_err());
+        assert_eq!(
+            parse("graph queries --timeout -0.5").err(),
+            Some("invalid value for --timeout: -0.5".to_string())
+        );
+        let options = parse("graph queries --memory-budget 3").unwrap();
+        assert_eq!(options.memory_budget, Some(3 << 20));
+        assert_eq!(
+            parse("graph queries --memory-budget 18446744073709551615").err(),
+            Some(
+                "invalid value for --memory-budget: 18446744073709551615 is too large".to_string()
+            )
+        );
+    }
+}
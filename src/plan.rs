@@ -1,6 +1,17 @@
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
 
 use egg::*;
+use nom::{
+    branch::alt,
+    bytes::complete::{take_until, take_while1},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{all_consuming, map},
+    multi::many0,
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+
+use crate::stats::Stats;
 
 #[derive(Clone, Hash, Ord, Eq, PartialEq, PartialOrd, Debug)]
 pub struct LabelMeta {
@@ -25,16 +36,70 @@ impl Display for LabelMeta {
     }
 }
 
+/// A constant query endpoint.
+///
+/// It is evaluated as a diagonal selector matrix with a single entry, so it
+/// is equal to its own transposition.
+#[derive(Clone, Hash, Ord, Eq, PartialEq, PartialOrd, Debug)]
+pub struct VertexMeta {
+    pub name: String,
+}
+
+impl FromStr for VertexMeta {
+    type Err = String;
+    // This is needed for the builtin egg parser. Only used in tests.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .ok_or(format!("not a vertex: {}", s))?;
+        Ok(VertexMeta {
+            name: name.to_string(),
+        })
+    }
+}
+
+impl Display for VertexMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{{}}}", self.name)
+    }
+}
+
 define_language! {
 pub enum Plan {
     Label(LabelMeta),
+    Vertex(VertexMeta),
     "/" = Seq([egg::Id; 2]),
     "|" = Alt([egg::Id; 2]),
     "*" = Star([egg::Id; 1]),
     "*r" = LStar([egg::Id; 2]),
     "l*" = RStar([egg::Id; 2]),
+    "t" = Transpose([egg::Id; 1]),
+    "vxm" = Vxm([egg::Id; 2]),
 } }
 
+fn is_vertex(var: &str) -> impl Fn(&mut EGraph<Plan, ()>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| {
+        egraph[subst[var]]
+            .nodes
+            .iter()
+            .any(|node| matches!(node, Plan::Vertex(_)))
+    }
+}
+
+/// Whether the class is a single row selected by a constant vertex, so it can
+/// be evaluated as a frontier vector.
+fn is_frontier(var: &str) -> impl Fn(&mut EGraph<Plan, ()>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| {
+        egraph[subst[var]]
+            .nodes
+            .iter()
+            .any(|node| matches!(node, Plan::Vertex(_) | Plan::Vxm(_)))
+    }
+}
+
 pub fn make_rules() -> Vec<egg::Rewrite<Plan, ()>> {
     vec![
         rewrite!("assoc-sec-1"; "(/ ?a (/ ?b ?c))" => "(/ (/ ?a ?b) ?c)"),
@@ -45,9 +110,468 @@ pub fn make_rules() -> Vec<egg::Rewrite<Plan, ()>> {
         rewrite!("distribute-2"; "(/ (| ?a ?b) ?c)" => "(| (/ ?a ?c) (/ ?b ?c))"),
         rewrite!("build-lstar"; "(/ ?a (* ?b))" => "(l* ?a ?b)"),
         rewrite!("build-rstar"; "(/ (* ?a) ?b)" => "(*r ?a ?b)"),
+        // Evaluating a transposed reversed sequence lets a constant destination
+        // become the starting point of a backward search.
+        rewrite!("reverse-seq"; "(/ ?a ?b)" => "(t (/ (t ?b) (t ?a)))"),
+        rewrite!("transpose-alt"; "(t (| ?a ?b))" => "(| (t ?a) (t ?b))"),
+        rewrite!("transpose-star"; "(t (* ?a))" => "(* (t ?a))"),
+        rewrite!("transpose-transpose"; "(t (t ?a))" => "?a"),
+        rewrite!("transpose-vertex"; "(t ?v)" => "?v" if is_vertex("?v")),
+        rewrite!("build-vxm"; "(/ ?v ?p)" => "(vxm ?v ?p)" if is_frontier("?v")),
     ]
 }
 
+/// Rewrite the plan so that transpositions only wrap labels.
+///
+/// Transpositions at the root are dropped: they do not change the number of
+/// answers, so the plan below them is evaluated as is.
+pub fn push_transposes(expr: &RecExpr<Plan>) -> RecExpr<Plan> {
+    fn aux(
+        src: &RecExpr<Plan>,
+        id: Id,
+        transposed: bool,
+        dst: &mut RecExpr<Plan>,
+        memo: &mut HashMap<(Id, bool), Id>,
+    ) -> Id {
+        if let Some(&res) = memo.get(&(id, transposed)) {
+            return res;
+        }
+        let mut go = |id, transposed| aux(src, id, transposed, dst, memo);
+        let node = match (&src[id], transposed) {
+            (&Plan::Transpose([a]), _) => {
+                let res = go(a, !transposed);
+                memo.insert((id, transposed), res);
+                return res;
+            }
+            (Plan::Label(_), true) => {
+                let label = go(id, false);
+                Plan::Transpose([label])
+            }
+            (node @ (Plan::Label(_) | Plan::Vertex(_)), _) => node.clone(),
+            (&Plan::Seq([a, b]), false) => Plan::Seq([go(a, false), go(b, false)]),
+            (&Plan::Seq([a, b]), true) => Plan::Seq([go(b, true), go(a, true)]),
+            (&Plan::Alt([a, b]), _) => Plan::Alt([go(a, transposed), go(b, transposed)]),
+            (&Plan::Star([a]), _) => Plan::Star([go(a, transposed)]),
+            (&Plan::LStar([a, b]), false) => Plan::LStar([go(a, false), go(b, false)]),
+            (&Plan::RStar([a, b]), false) => Plan::RStar([go(a, false), go(b, false)]),
+            // (a* / b)^T = b^T / (a^T)* and vice versa.
+            (&Plan::LStar([a, b]), true) => Plan::RStar([go(b, true), go(a, true)]),
+            (&Plan::RStar([a, b]), true) => Plan::LStar([go(b, true), go(a, true)]),
+            (&Plan::Vxm([a, b]), false) => Plan::Vxm([go(a, false), go(b, false)]),
+            (&Plan::Vxm([a, b]), true) => Plan::Seq([go(b, true), go(a, true)]),
+        };
+        let res = dst.add(node);
+        memo.insert((id, transposed), res);
+        res
+    }
+
+    let mut res = RecExpr::default();
+    if expr.as_ref().is_empty() {
+        return res;
+    }
+    let mut root = Id::from(expr.as_ref().len() - 1);
+    while let Plan::Transpose([inner]) = expr[root] {
+        root = inner;
+    }
+    aux(expr, root, false, &mut res, &mut HashMap::new());
+    res
+}
+
+/// A rough estimate of the plan evaluation.
+///
+/// Estimates are ordered by the amount of work first.
+#[derive(Clone, Debug)]
+pub struct Estimate {
+    pub work: f64,
+    pub nvals: f64,
+    /// Name of the label when the plan is a single label, to look up its
+    /// statistics. It does not take part in comparisons.
+    pub label: Option<String>,
+}
+
+impl PartialEq for Estimate {
+    fn eq(&self, other: &Self) -> bool {
+        (self.work, self.nvals) == (other.work, other.nvals)
+    }
+}
+
+impl PartialOrd for Estimate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.work, self.nvals).partial_cmp(&(other.work, other.nvals))
+    }
+}
+
+/// Cost function preferring plans with small intermediate results.
+///
+/// Labels are assumed to be uniformly distributed among `nverts` vertices.
+/// A sequence scans its left operand and expands each of its entries with
+/// the average degree of the right one, so plans starting from a selective
+/// operand (e.g. a constant vertex) are cheaper. Every matrix operation also
+/// pays for its `nverts` rows, while a frontier expansion only touches the
+/// rows reachable from the frontier.
+///
+/// With several `workers` the independent operands of a node are evaluated
+/// concurrently: they cost as much as the more expensive one plus a share of
+/// the cheaper one for the threads they compete for.
+///
+/// With the `stats` of the graph, a sequence of two labels is estimated from
+/// the number of targets of the first which are sources of the second instead.
+#[derive(Clone)]
+pub struct EstimateCostFn {
+    pub nverts: usize,
+    pub workers: usize,
+    pub stats: Option<Arc<Stats>>,
+}
+
+impl EstimateCostFn {
+    /// Work of evaluating two independent operands.
+    fn operands(&self, a: &Estimate, b: &Estimate) -> f64 {
+        a.work.max(b.work) + a.work.min(b.work) / self.workers.max(1) as f64
+    }
+
+    /// Closure of `from` entries through `step`: each reached entry is
+    /// expanded once.
+    fn closure(&self, from: f64, step: &Estimate) -> (f64, f64) {
+        let n = self.nverts.max(2) as f64;
+        let degree = step.nvals / n;
+        let nvals = (from * (1.0 + degree) * n.log2()).min(from * n).min(n * n);
+        (nvals, nvals * (1.0 + degree))
+    }
+
+    /// Size of the sequence of two labels from their statistics: the entries
+    /// of `a` reaching targets shared with the sources of `b`, each expanded
+    /// with the average out-degree of `b`.
+    fn compose(&self, a: &Estimate, b: &Estimate) -> Option<f64> {
+        let stats = self.stats.as_ref()?;
+        let (lhs, rhs) = (a.label.as_ref()?, b.label.as_ref()?);
+        let targets = stats.labels.get(lhs)?.targets.max(1) as f64;
+        let degree = stats.labels.get(rhs)?.avg_out();
+        let common = stats
+            .composability
+            .get(&(lhs.clone(), rhs.clone()))
+            .map_or(0.0, |&common| common as f64);
+        Some(a.nvals * common / targets * degree)
+    }
+}
+
+impl CostFunction<Plan> for EstimateCostFn {
+    type Cost = Estimate;
+    fn cost<C>(&mut self, enode: &Plan, mut costs: C) -> Self::Cost
+    where
+        C: FnMut(Id) -> Self::Cost,
+    {
+        let n = self.nverts.max(1) as f64;
+        match enode {
+            Plan::Label(meta) => Estimate {
+                work: 0.0,
+                nvals: meta.nvals as f64,
+                label: Some(meta.name.clone()),
+            },
+            Plan::Vertex(_) => Estimate {
+                work: 0.0,
+                nvals: 1.0,
+                label: None,
+            },
+            // Transposed labels are cached by the graph.
+            Plan::Transpose([a]) => Estimate {
+                label: None,
+                ..costs(*a)
+            },
+            Plan::Seq([a, b]) => {
+                let (a, b) = (costs(*a), costs(*b));
+                let nvals = self.compose(&a, &b).unwrap_or(a.nvals * b.nvals / n);
+                Estimate {
+                    work: self.operands(&a, &b) + n + a.nvals + nvals,
+                    nvals: nvals.min(n * n),
+                    label: None,
+                }
+            }
+            Plan::Vxm([a, b]) => {
+                let (a, b) = (costs(*a), costs(*b));
+                let nvals = a.nvals * b.nvals / n;
+                Estimate {
+                    work: a.work + a.nvals + nvals + b.work * (a.nvals / n).min(1.0),
+                    nvals: nvals.min(n),
+                    label: None,
+                }
+            }
+            Plan::Alt([a, b]) => {
+                let (a, b) = (costs(*a), costs(*b));
+                Estimate {
+                    work: self.operands(&a, &b) + n + a.nvals + b.nvals,
+                    nvals: (a.nvals + b.nvals).min(n * n),
+                    label: None,
+                }
+            }
+            Plan::Star([a]) => {
+                let a = costs(*a);
+                let (nvals, work) = self.closure(n, &a);
+                Estimate {
+                    work: a.work + n + work,
+                    nvals,
+                    label: None,
+                }
+            }
+            Plan::LStar([a, b]) => {
+                let (a, b) = (costs(*a), costs(*b));
+                let (nvals, work) = self.closure(b.nvals, &a);
+                Estimate {
+                    work: self.operands(&a, &b) + n + work,
+                    nvals,
+                    label: None,
+                }
+            }
+            Plan::RStar([a, b]) => {
+                let (a, b) = (costs(*a), costs(*b));
+                let (nvals, work) = self.closure(a.nvals, &b);
+                Estimate {
+                    work: self.operands(&a, &b) + n + work,
+                    nvals,
+                    label: None,
+                }
+            }
+        }
+    }
+}
+
+/// Estimate every node of the plan, indexed like its nodes.
+pub fn estimate_nodes(expr: &RecExpr<Plan>, mut cost_fn: EstimateCostFn) -> Vec<Estimate> {
+    let mut estimates: Vec<Estimate> = Vec::with_capacity(expr.as_ref().len());
+    for node in expr.as_ref() {
+        let estimate = cost_fn.cost(node, |child| estimates[usize::from(child)].clone());
+        estimates.push(estimate);
+    }
+    estimates
+}
+
+/// Extract the cheapest plan of the class with a root satisfying `accept`.
+pub fn extract_rooted(
+    egraph: &EGraph<Plan, ()>,
+    class: Id,
+    mut cost_fn: EstimateCostFn,
+    accept: impl Fn(&Plan) -> bool,
+) -> Option<RecExpr<Plan>> {
+    let extractor = Extractor::new(egraph, cost_fn.clone());
+    egraph[class]
+        .nodes
+        .iter()
+        .filter(|node| accept(node))
+        .map(|node| {
+            let cost = cost_fn.cost(node, |child| extractor.find_best_cost(child));
+            (cost, node)
+        })
+        .min_by(|(lhs, _), (rhs, _)| lhs.partial_cmp(rhs).unwrap_or(Ordering::Equal))
+        .map(|(_, node)| node.join_recexprs(|child| extractor.find_best(child).1))
+}
+
+/// Count distinct acyclic plans represented by the class, saturating at
+/// `u128::MAX`.
+pub fn count_plans(egraph: &EGraph<Plan, ()>, root: Id) -> u128 {
+    // Returns the count and the lowest depth of a class on the stack that was
+    // cut off. Counts not depending on the stack are memoized.
+    fn aux(
+        egraph: &EGraph<Plan, ()>,
+        id: Id,
+        stack: &mut HashMap<Id, usize>,
+        memo: &mut HashMap<Id, u128>,
+    ) -> (u128, usize) {
+        let id = egraph.find(id);
+        if let Some(&count) = memo.get(&id) {
+            return (count, usize::MAX);
+        }
+        if let Some(&depth) = stack.get(&id) {
+            return (0, depth);
+        }
+        let depth = stack.len();
+        stack.insert(id, depth);
+        let mut lowest = usize::MAX;
+        let count = egraph[id].nodes.iter().fold(0u128, |total, node| {
+            let count = node.children().iter().fold(1u128, |count, &child| {
+                let (child_count, child_lowest) = aux(egraph, child, stack, memo);
+                lowest = lowest.min(child_lowest);
+                count.saturating_mul(child_count)
+            });
+            total.saturating_add(count)
+        });
+        stack.remove(&id);
+        if lowest >= depth {
+            memo.insert(id, count);
+            lowest = usize::MAX;
+        }
+        (count, lowest)
+    }
+
+    aux(egraph, root, &mut HashMap::new(), &mut HashMap::new()).0
+}
+
+/// Enumerate up to `cap` distinct acyclic plans represented by the class.
+pub fn enumerate_plans(egraph: &EGraph<Plan, ()>, root: Id, cap: usize) -> Vec<RecExpr<Plan>> {
+    fn append(dst: &mut RecExpr<Plan>, src: &RecExpr<Plan>) -> Id {
+        let offset = dst.as_ref().len();
+        for node in src.as_ref() {
+            dst.add(
+                node.clone()
+                    .map_children(|child| Id::from(usize::from(child) + offset)),
+            );
+        }
+        Id::from(dst.as_ref().len() - 1)
+    }
+
+    // Same as in `count_plans`, plans not depending on the stack are memoized.
+    fn aux(
+        egraph: &EGraph<Plan, ()>,
+        id: Id,
+        cap: usize,
+        stack: &mut Vec<Id>,
+        memo: &mut HashMap<Id, Vec<RecExpr<Plan>>>,
+    ) -> (Vec<RecExpr<Plan>>, usize) {
+        let id = egraph.find(id);
+        if let Some(plans) = memo.get(&id) {
+            return (plans.clone(), usize::MAX);
+        }
+        if let Some(depth) = stack.iter().position(|&class| class == id) {
+            return (vec![], depth);
+        }
+        let depth = stack.len();
+        stack.push(id);
+        let mut lowest = usize::MAX;
+        let mut plans = vec![];
+        for node in egraph[id].nodes.iter() {
+            if plans.len() >= cap {
+                break;
+            }
+            let mut children = vec![];
+            for &child in node.children() {
+                let (child_plans, child_lowest) = aux(egraph, child, cap, stack, memo);
+                lowest = lowest.min(child_lowest);
+                children.push(child_plans);
+            }
+            // Cartesian product of the children plans.
+            let mut partial: Vec<Vec<&RecExpr<Plan>>> = vec![vec![]];
+            for child_plans in children.iter() {
+                partial = partial
+                    .iter()
+                    .flat_map(|prefix| {
+                        child_plans.iter().map(move |plan| {
+                            let mut prefix = prefix.clone();
+                            prefix.push(plan);
+                            prefix
+                        })
+                    })
+                    .take(cap - plans.len())
+                    .collect();
+            }
+            plans.extend(partial.into_iter().map(|args| {
+                let mut expr = RecExpr::default();
+                let ids: Vec<Id> = args.iter().map(|arg| append(&mut expr, arg)).collect();
+                let mut ids = ids.into_iter();
+                expr.add(node.clone().map_children(|_| ids.next().unwrap()));
+                expr
+            }));
+        }
+        stack.pop();
+        if lowest >= depth {
+            memo.insert(id, plans.clone());
+            lowest = usize::MAX;
+        }
+        (plans, lowest)
+    }
+
+    aux(egraph, root, cap, &mut vec![], &mut HashMap::new()).0
+}
+
+/// Write the plan keeping label and vertex names, so it can be read back with
+/// [`parse_plan`].
+///
+/// Labels are written as `<name>`, vertices as `{name}`, operators as egg
+/// s-expressions, e.g. `(vxm {v} (* <a>))`.
+pub fn write_plan(expr: &RecExpr<Plan>) -> String {
+    fn aux(expr: &RecExpr<Plan>, id: Id, res: &mut String) {
+        match &expr[id] {
+            Plan::Label(meta) => {
+                res.push('<');
+                res.push_str(&meta.name);
+                res.push('>');
+            }
+            Plan::Vertex(meta) => res.push_str(&meta.to_string()),
+            op => {
+                res.push('(');
+                res.push_str(&op.to_string());
+                for &child in op.children() {
+                    res.push(' ');
+                    aux(expr, child, res);
+                }
+                res.push(')');
+            }
+        }
+    }
+
+    let mut res = String::new();
+    aux(expr, Id::from(expr.as_ref().len() - 1), &mut res);
+    res
+}
+
+enum PlanTree {
+    Label(String),
+    Vertex(String),
+    Op(String, Vec<PlanTree>),
+}
+
+fn plan_tree(input: &str) -> IResult<&str, PlanTree> {
+    alt((
+        delimited(
+            char('<'),
+            map(take_until(">"), |s: &str| PlanTree::Label(s.to_string())),
+            char('>'),
+        ),
+        delimited(
+            char('{'),
+            map(take_until("}"), |s: &str| PlanTree::Vertex(s.to_string())),
+            char('}'),
+        ),
+        delimited(
+            tuple((char('('), multispace0)),
+            map(
+                tuple((
+                    take_while1(|c: char| !c.is_whitespace() && c != '(' && c != ')'),
+                    many0(preceded(multispace1, plan_tree)),
+                )),
+                |(op, args): (&str, _)| PlanTree::Op(op.to_string(), args),
+            ),
+            tuple((multispace0, char(')'))),
+        ),
+    ))(input)
+}
+
+/// Read a plan written by [`write_plan`].
+///
+/// Label sizes are unknown, so they are set to zero.
+pub fn parse_plan(s: &str) -> Result<RecExpr<Plan>, String> {
+    fn aux(tree: PlanTree, expr: &mut RecExpr<Plan>) -> Result<Id, String> {
+        let node = match tree {
+            PlanTree::Label(name) => Plan::Label(LabelMeta { name, nvals: 0 }),
+            PlanTree::Vertex(name) => Plan::Vertex(VertexMeta { name }),
+            PlanTree::Op(op, args) => {
+                let args = args
+                    .into_iter()
+                    .map(|arg| aux(arg, expr))
+                    .collect::<Result<Vec<Id>, String>>()?;
+                if op.parse::<LabelMeta>().is_ok() || op.parse::<VertexMeta>().is_ok() {
+                    return Err(format!("not an operator: {}", op));
+                }
+                Plan::from_op(&op, args).map_err(|err| err.to_string())?
+            }
+        };
+        Ok(expr.add(node))
+    }
+
+    let (_, tree) = all_consuming(delimited(multispace0, plan_tree, multispace0))(s)
+        .map_err(|err| format!("unable to parse plan: {}", err))?;
+    let mut expr = RecExpr::default();
+    aux(tree, &mut expr)?;
+    Ok(expr)
+}
+
 pub struct RandomCostFn;
 impl CostFunction<Plan> for RandomCostFn {
     type Cost = f64;
@@ -62,6 +586,7 @@ impl CostFunction<Plan> for RandomCostFn {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stats::LabelStats;
     use expect_test::expect;
 
     pub struct CostFn;
@@ -73,11 +598,14 @@ mod tests {
         {
             match enode {
                 Plan::Label(meta) => meta.nvals as f64,
+                Plan::Vertex(_) => 1.0,
                 Plan::Seq(args) => costs(args[0]).min(costs(args[1])).powf(1.1),
                 Plan::Alt(args) => costs(args[0]).min(costs(args[1])).powf(1.1),
                 Plan::Star(args) => costs(args[0]).powi(2),
                 Plan::LStar(args) => costs(args[0]) * costs(args[1]),
                 Plan::RStar(args) => costs(args[0]) * costs(args[1]),
+                Plan::Transpose(args) => costs(args[0]) + 1.0,
+                Plan::Vxm(args) => costs(args[0]).min(costs(args[1])).powf(1.1),
             }
         }
     }
@@ -113,4 +641,114 @@ mod tests {
         expect![[r#"(| "(-, 3)" (| "(-, 1)" (| "(-, 4)" "(-, 2)")))"#]]
             .assert_eq(test_simplify("(| (| (| 4 3) 2) 1)".to_string()).as_str());
     }
+
+    #[test]
+    fn test_push_transposes() {
+        let expr = "(t (/ {v} (t (/ 1 (* 2)))))".parse().unwrap();
+        expect![[r#"(/ "{v}" (/ (* (t "(-, 2)")) (t "(-, 1)")))"#]]
+            .assert_eq(push_transposes(&expr).to_string().as_str());
+        assert!(push_transposes(&RecExpr::default()).as_ref().is_empty());
+    }
+
+    #[test]
+    fn test_estimate_dest_con() {
+        let expr = "(/ (/ 100 200) {v})".parse().unwrap();
+        let runner = Runner::default().with_expr(&expr).run(&make_rules());
+        let extractor = Extractor::new(
+            &runner.egraph,
+            EstimateCostFn {
+                nverts: 100,
+                workers: 1,
+                stats: None,
+            },
+        );
+        expect![[r#"(t (vxm "{v}" (t (/ "(-, 100)" "(-, 200)"))))"#]]
+            .assert_eq(extractor.find_best(runner.roots[0]).1.to_string().as_str());
+    }
+
+    #[test]
+    fn test_estimate_src_con() {
+        let expr = "(/ {v} (/ 100 (* 200)))".parse().unwrap();
+        let runner = Runner::default().with_expr(&expr).run(&make_rules());
+        let extractor = Extractor::new(
+            &runner.egraph,
+            EstimateCostFn {
+                nverts: 100,
+                workers: 1,
+                stats: None,
+            },
+        );
+        expect![[r#"(vxm "{v}" (l* "(-, 100)" "(-, 200)"))"#]]
+            .assert_eq(extractor.find_best(runner.roots[0]).1.to_string().as_str());
+    }
+
+    #[test]
+    fn test_estimate_workers() {
+        let expr: RecExpr<Plan> = "(| (/ 100 200) (/ 300 400))".parse().unwrap();
+        let estimate = |workers| {
+            EstimateCostFn {
+                nverts: 100,
+                workers,
+                stats: None,
+            }
+            .cost_rec(&expr)
+        };
+        assert!(estimate(4).work < estimate(1).work);
+        assert_eq!(estimate(4).nvals, estimate(1).nvals);
+    }
+
+    #[test]
+    fn test_estimate_stats() {
+        let expr: RecExpr<Plan> = "(/ 100 200)".parse().unwrap();
+        let mut stats = Stats::default();
+        let label = LabelStats {
+            nvals: 100,
+            sources: 50,
+            targets: 10,
+            ..LabelStats::default()
+        };
+        stats.labels.insert("-".to_string(), label);
+        let estimate = |stats: Option<&Stats>| {
+            EstimateCostFn {
+                nverts: 100,
+                workers: 1,
+                stats: stats.cloned().map(Arc::new),
+            }
+            .cost_rec(&expr)
+            .nvals
+        };
+        assert_eq!(estimate(None), 200.0);
+        // No target of the label is a source of it.
+        assert_eq!(estimate(Some(&stats)), 0.0);
+        // 100 entries reach 5 of 10 targets, expanded 2 times each.
+        let pair = ("-".to_string(), "-".to_string());
+        stats.composability.insert(pair, 5);
+        assert_eq!(estimate(Some(&stats)), 100.0);
+    }
+
+    #[test]
+    fn test_enumerate_plans() {
+        let expr = "(/ 1 (| 2 3))".parse().unwrap();
+        let runner = Runner::default().with_expr(&expr).run(&make_rules());
+        let count = count_plans(&runner.egraph, runner.roots[0]);
+        let plans = enumerate_plans(&runner.egraph, runner.roots[0], usize::MAX);
+        assert_eq!(plans.len() as u128, count);
+        let distinct: std::collections::HashSet<String> =
+            plans.iter().map(|plan| plan.to_string()).collect();
+        assert_eq!(distinct.len(), plans.len());
+        expect!["36"].assert_eq(count.to_string().as_str());
+        assert_eq!(enumerate_plans(&runner.egraph, runner.roots[0], 5).len(), 5);
+    }
+
+    #[test]
+    fn test_write_parse_plan() {
+        let plan = "(t (vxm {a b} (| (l* <x> <y/z>) (t <x>))))";
+        let expr = parse_plan(plan).unwrap();
+        expect![[r#"(t (vxm "{a b}" (| (l* "(x, 0)" "(y/z, 0)") (t "(x, 0)"))))"#]]
+            .assert_eq(expr.to_string().as_str());
+        assert_eq!(write_plan(&expr), plan);
+        assert!(parse_plan("(/ <x>)").is_err());
+        assert!(parse_plan("(1 <x> <y>)").is_err());
+        assert!(parse_plan("(/ <x> <y>) <z>").is_err());
+    }
 }
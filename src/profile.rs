@@ -0,0 +1,122 @@
+use std::fmt;
+use std::time::Duration;
+
+use egg::{Id, Language, RecExpr};
+
+use crate::plan::Plan;
+
+/// Statistics of a plan node gathered while evaluating it.
+///
+/// Nodes below a closure evaluated on a frontier are expanded once per
+/// iteration, so their time and calls add up while `nvals` is the size of
+/// the last result.
+#[derive(Clone, Debug, Default)]
+pub struct NodeProfile {
+    pub calls: usize,
+    pub time: Duration,
+    pub nvals: u64,
+    /// Fixpoint iterations of `*`, `*r` and `l*`.
+    pub iterations: usize,
+}
+
+/// Per-node execution profile of a plan, indexed like the nodes of the
+/// evaluated (transpose-normalized) plan.
+pub struct Profile {
+    pub expr: RecExpr<Plan>,
+    pub nodes: Vec<NodeProfile>,
+    /// Whether the time and iterations of every node were measured, or only
+    /// the result sizes and the total time at the root.
+    pub timed: bool,
+}
+
+impl Profile {
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>, id: Id, depth: usize) -> fmt::Result {
+        let node = &self.expr[id];
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        match node {
+            Plan::Label(meta) => write!(f, "<{}>", meta.name)?,
+            Plan::Vertex(meta) => write!(f, "{}", meta)?,
+            op => write!(f, "{}", op)?,
+        }
+        let stats = &self.nodes[usize::from(id)];
+        let is_root = usize::from(id) == self.nodes.len() - 1;
+        if stats.calls == 0 {
+            writeln!(f)?;
+        } else if !self.timed {
+            write!(f, "  nvals={}", stats.nvals)?;
+            if is_root {
+                write!(f, " total time={:?}", stats.time)?;
+            }
+            writeln!(f)?;
+        } else {
+            write!(f, "  time={:?} nvals={}", stats.time, stats.nvals)?;
+            if stats.calls > 1 {
+                write!(f, " calls={}", stats.calls)?;
+            }
+            if matches!(node, Plan::Star(_) | Plan::LStar(_) | Plan::RStar(_)) {
+                write!(f, " iterations={}", stats.iterations)?;
+            }
+            writeln!(f)?;
+        }
+        node.children()
+            .iter()
+            .try_for_each(|&child| self.fmt_node(f, child, depth + 1))
+    }
+}
+
+/// Print the plan as an indented tree annotated with the node statistics.
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_node(f, Id::from(self.expr.as_ref().len() - 1), 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::parse_plan;
+    use expect_test::expect;
+
+    #[test]
+    fn test_profile_display() {
+        let expr = parse_plan("(/ (* <a>) <b>)").unwrap();
+        let mut profile = Profile {
+            nodes: vec![NodeProfile::default(); expr.as_ref().len()],
+            expr,
+            timed: true,
+        };
+        profile.nodes[0] = NodeProfile {
+            calls: 1,
+            time: Duration::from_millis(1),
+            nvals: 10,
+            iterations: 0,
+        };
+        profile.nodes[1] = NodeProfile {
+            calls: 1,
+            time: Duration::from_millis(5),
+            nvals: 40,
+            iterations: 3,
+        };
+        profile.nodes[3] = NodeProfile {
+            calls: 1,
+            time: Duration::from_millis(7),
+            nvals: 25,
+            iterations: 0,
+        };
+        expect![[r#"
+            /  time=7ms nvals=25
+              *  time=5ms nvals=40 iterations=3
+                <a>  time=1ms nvals=10
+              <b>
+        "#]]
+        .assert_eq(&profile.to_string());
+        profile.timed = false;
+        expect![[r#"
+            /  nvals=25 total time=7ms
+              *  nvals=40
+                <a>  nvals=10
+              <b>
+        "#]]
+        .assert_eq(&profile.to_string());
+    }
+}
@@ -28,6 +28,54 @@ pub struct Query {
     pub dest: Vertex,
 }
 
+/// A hint on how to evaluate a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hint {
+    /// Evaluate the given plan written with real label names.
+    Plan(String),
+    /// Evaluate the pattern from its source.
+    FromSrc,
+    /// Evaluate the transposed pattern from its destination.
+    FromDest,
+    /// Do not distribute sequences over alternatives.
+    NoDistribute,
+    /// Evaluate the query with the given number of GraphBLAS threads.
+    Threads(i32),
+    /// Show a path from the first to the second vertex matching the pattern.
+    Witness(String, String),
+}
+
+/// Parse either `plan <plan>` or a whitespace separated list of other hints.
+pub fn parse_hints(s: &str) -> Result<Vec<Hint>, String> {
+    let s = s.trim();
+    if let Some(plan) = s.strip_prefix("plan ") {
+        return Ok(vec![Hint::Plan(plan.trim().to_string())]);
+    }
+    let hints = s
+        .split_whitespace()
+        .map(|hint| match hint {
+            "from-src" => Ok(Hint::FromSrc),
+            "from-dest" => Ok(Hint::FromDest),
+            "no-distribute" => Ok(Hint::NoDistribute),
+            _ if hint.starts_with("witness=") => match hint["witness=".len()..].split_once(',') {
+                Some((src, dest)) if !src.is_empty() && !dest.is_empty() => {
+                    Ok(Hint::Witness(src.to_string(), dest.to_string()))
+                }
+                _ => Err(format!("invalid witness pair: {}", hint)),
+            },
+            _ => match hint.strip_prefix("threads=").map(str::parse) {
+                Some(Ok(nthreads)) if nthreads > 0 => Ok(Hint::Threads(nthreads)),
+                Some(_) => Err(format!("invalid thread count: {}", hint)),
+                None => Err(format!("unknown hint: {}", hint)),
+            },
+        })
+        .collect::<Result<Vec<Hint>, String>>()?;
+    if hints.contains(&Hint::FromSrc) && hints.contains(&Hint::FromDest) {
+        return Err("from-src and from-dest hints conflict".to_string());
+    }
+    Ok(hints)
+}
+
 fn parse_query(input: &str) -> IResult<&str, Query> {
     let (input, src) = parse_vertex(input)?;
     let (input, pattern) = parse_pattern(input)?;
@@ -134,6 +182,68 @@ impl FromStr for Query {
     }
 }
 
+/// Automaton of a pattern with a single start and accepting state.
+/// Transitions labelled with `None` consume no edge.
+#[derive(Debug)]
+pub struct Nfa {
+    pub moves: Vec<Vec<(Option<String>, usize)>>,
+    pub start: usize,
+    pub accept: usize,
+}
+
+impl Nfa {
+    fn state(&mut self) -> usize {
+        self.moves.push(vec![]);
+        self.moves.len() - 1
+    }
+
+    /// Add states and transitions matching the pattern from `from` to `to`.
+    fn connect(&mut self, pattern: &Pattern, from: usize, to: usize) {
+        match pattern {
+            Pattern::Uri(uri) => self.moves[from].push((Some(uri.clone()), to)),
+            Pattern::Seq(lhs, rhs) => {
+                let mid = self.state();
+                self.connect(lhs, from, mid);
+                self.connect(rhs, mid, to);
+            }
+            Pattern::Alt(lhs, rhs) => {
+                self.connect(lhs, from, to);
+                self.connect(rhs, from, to);
+            }
+            Pattern::Star(pattern) => {
+                let mid = self.state();
+                self.moves[from].push((None, mid));
+                self.connect(pattern, mid, mid);
+                self.moves[mid].push((None, to));
+            }
+            Pattern::Plus(pattern) => {
+                let mid = self.state();
+                self.connect(pattern, from, mid);
+                self.connect(pattern, mid, mid);
+                self.moves[mid].push((None, to));
+            }
+            Pattern::Opt(pattern) => {
+                self.moves[from].push((None, to));
+                self.connect(pattern, from, to);
+            }
+        }
+    }
+}
+
+impl Pattern {
+    /// Build the Thompson automaton recognizing the label sequences of the
+    /// pattern.
+    pub fn nfa(&self) -> Nfa {
+        let mut nfa = Nfa {
+            moves: vec![vec![], vec![]],
+            start: 0,
+            accept: 1,
+        };
+        nfa.connect(self, nfa.start, nfa.accept);
+        nfa
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,4 +284,29 @@ mod tests {
         expect![[r#"Query { src: Any, pattern: Seq(Seq(Seq(Uri("a"), Opt(Uri("b"))), Opt(Uri("b"))), Opt(Uri("b"))), dest: Con("e") }"#]]
             .assert_eq(format!("{:?}", "?x <a>/<b>?/<b>?/<b>? <e>".parse::<Query>().unwrap()).as_str());
     }
+    #[test]
+    fn test_hints() {
+        expect![[r#"Ok([FromDest, NoDistribute])"#]]
+            .assert_eq(format!("{:?}", parse_hints(" from-dest  no-distribute")).as_str());
+        expect![[r#"Ok([Plan("(/ <a> (t <b>))")])"#]]
+            .assert_eq(format!("{:?}", parse_hints("plan (/ <a> (t <b>))")).as_str());
+        expect![[r#"Err("from-src and from-dest hints conflict")"#]]
+            .assert_eq(format!("{:?}", parse_hints("from-src from-dest")).as_str());
+        expect![[r#"Err("unknown hint: fast")"#]]
+            .assert_eq(format!("{:?}", parse_hints("fast")).as_str());
+        expect![[r#"Ok([FromSrc, Threads(4)])"#]]
+            .assert_eq(format!("{:?}", parse_hints("from-src threads=4")).as_str());
+        expect![[r#"Err("invalid thread count: threads=0")"#]]
+            .assert_eq(format!("{:?}", parse_hints("threads=0")).as_str());
+        expect![[r#"Ok([Witness("1", "7")])"#]]
+            .assert_eq(format!("{:?}", parse_hints("witness=1,7")).as_str());
+        expect![[r#"Err("invalid witness pair: witness=1")"#]]
+            .assert_eq(format!("{:?}", parse_hints("witness=1")).as_str());
+    }
+    #[test]
+    fn test_nfa() {
+        let query = "?x <a>/(<b>|<c>)* ?y".parse::<Query>().unwrap();
+        expect![[r#"Nfa { moves: [[(Some("a"), 2)], [], [(None, 3)], [(Some("b"), 3), (Some("c"), 3), (None, 1)]], start: 0, accept: 1 }"#]]
+            .assert_eq(format!("{:?}", query.pattern.nfa()).as_str());
+    }
 }
@@ -0,0 +1,274 @@
+use std::{fmt, str::FromStr};
+
+use egg::{Id, RecExpr};
+
+use crate::eval::{selector, Cancel, EvalError};
+use crate::graph::Graph;
+use crate::grb::{self, Matrix, OwnedMatrix};
+use crate::plan::{push_transposes, Plan};
+
+/// Semiring a plan is evaluated over. Every edge is a path of length 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Semiring {
+    /// Whether a matching path exists.
+    Boolean,
+    /// Number of matching paths. With a bound, every closure repeats its
+    /// pattern at most that many times; without it closures over cycles
+    /// have infinitely many paths and are rejected.
+    ///
+    /// Paths are counted once per way the plan matches them, so ambiguous
+    /// patterns like `<a>|<a>` or `<a>*/<a>*` count some paths several times.
+    Counting(Option<usize>),
+    /// Length of the shortest matching path.
+    Tropical,
+}
+
+/// Parse `boolean`, `counting`, `counting:<bound>` or `tropical`.
+impl FromStr for Semiring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "boolean" => Ok(Semiring::Boolean),
+            None if s == "counting" => Ok(Semiring::Counting(None)),
+            None if s == "tropical" => Ok(Semiring::Tropical),
+            Some(("counting", bound)) => bound
+                .parse()
+                .map(|bound| Semiring::Counting(Some(bound)))
+                .map_err(|_| format!("invalid path length bound: {}", bound)),
+            _ => Err(format!("unknown semiring: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Semiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Semiring::Boolean => write!(f, "boolean"),
+            Semiring::Counting(None) => write!(f, "counting"),
+            Semiring::Counting(Some(bound)) => write!(f, "counting:{}", bound),
+            Semiring::Tropical => write!(f, "tropical"),
+        }
+    }
+}
+
+impl Semiring {
+    /// Convert a boolean label matrix into the semiring domain.
+    fn label(self, mat: &Matrix) -> Result<OwnedMatrix, String> {
+        match self {
+            Semiring::Boolean => mat.dup(),
+            _ => unsafe { mat.apply(grb::GrB_UINT64, grb::GrB_IDENTITY_UINT64) },
+        }
+    }
+
+    /// Matrix of empty paths.
+    fn unit(self, size: grb::Index) -> Result<OwnedMatrix, String> {
+        match self {
+            Semiring::Boolean => OwnedMatrix::identity(size),
+            Semiring::Counting(_) => OwnedMatrix::diagonal_u64(size, 1),
+            Semiring::Tropical => OwnedMatrix::diagonal_u64(size, 0),
+        }
+    }
+
+    fn add(self, lhs: &Matrix, rhs: &Matrix) -> Result<OwnedMatrix, String> {
+        unsafe {
+            match self {
+                Semiring::Boolean => lhs.ewise_add(rhs, grb::GrB_BOOL, grb::GrB_LOR),
+                Semiring::Counting(_) => lhs.ewise_add(rhs, grb::GrB_UINT64, grb::GrB_PLUS_UINT64),
+                Semiring::Tropical => lhs.ewise_add(rhs, grb::GrB_UINT64, grb::GrB_MIN_UINT64),
+            }
+        }
+    }
+
+    fn mul(self, lhs: &Matrix, rhs: &Matrix) -> Result<OwnedMatrix, String> {
+        unsafe {
+            match self {
+                Semiring::Boolean => lhs.mxm(rhs),
+                Semiring::Counting(_) => {
+                    lhs.mxm_semiring(rhs, grb::GrB_UINT64, grb::GrB_PLUS_TIMES_SEMIRING_UINT64)
+                }
+                Semiring::Tropical => {
+                    lhs.mxm_semiring(rhs, grb::GrB_UINT64, grb::GrB_MIN_PLUS_SEMIRING_UINT64)
+                }
+            }
+        }
+    }
+
+    /// Compute `step*`, the sum of all powers of `step`.
+    fn star(self, step: &Matrix, cancel: &Cancel) -> Result<OwnedMatrix, EvalError> {
+        let size = step.nrows()?;
+        let mut res = self.unit(size)?;
+        match self {
+            Semiring::Counting(bound) => {
+                if bound.is_none() && has_cycle(step, cancel)? {
+                    return Err(EvalError::Failed(
+                        "closure over a cycle has infinitely many paths, bound their length"
+                            .to_string(),
+                    ));
+                }
+                // Powers vanish after the longest path of an acyclic graph.
+                let mut power = self.unit(size)?;
+                for _ in 1..=bound.unwrap_or(usize::MAX) {
+                    cancel.check()?;
+                    power = self.mul(&power, step)?;
+                    if power.nvals()? == 0 {
+                        break;
+                    }
+                    res = self.add(&res, &power)?;
+                }
+            }
+            // Reachability and shortest paths converge in at most `size`
+            // iterations.
+            Semiring::Boolean | Semiring::Tropical => loop {
+                cancel.check()?;
+                let next = self.add(&res, &*self.mul(&res, step)?)?;
+                if next.is_equal(&res)? {
+                    break;
+                }
+                res = next;
+            },
+        }
+        Ok(res)
+    }
+
+    /// Evaluate the node from the values of the nodes before it.
+    fn node(
+        self,
+        graph: &Graph,
+        expr: &RecExpr<Plan>,
+        values: &[OwnedMatrix],
+        id: usize,
+        cancel: &Cancel,
+    ) -> Result<OwnedMatrix, EvalError> {
+        cancel.check()?;
+        let value = |child: Id| &*values[usize::from(child)];
+        Ok(match &expr.as_ref()[id] {
+            Plan::Label(meta) => self.label(&*graph.matrix(&meta.name)?)?,
+            &Plan::Transpose([label]) => match &expr[label] {
+                Plan::Label(meta) => self.label(&*graph.transposed(&meta.name)?)?,
+                _ => return Err("only labels can be transposed".to_string().into()),
+            },
+            Plan::Vertex(meta) => self.label(&OwnedMatrix(selector(graph, &meta.name)?))?,
+            &Plan::Seq([lhs, rhs]) | &Plan::Vxm([lhs, rhs]) => self.mul(value(lhs), value(rhs))?,
+            &Plan::Alt([lhs, rhs]) => self.add(value(lhs), value(rhs))?,
+            &Plan::Star([lhs]) => self.star(value(lhs), cancel)?,
+            &Plan::LStar([lhs, rhs]) => self.mul(&*self.star(value(lhs), cancel)?, value(rhs))?,
+            &Plan::RStar([lhs, rhs]) => self.mul(value(lhs), &*self.star(value(rhs), cancel)?)?,
+        })
+    }
+
+    /// Describe a result matrix: its number of pairs and the number of paths
+    /// or the longest of the shortest paths.
+    pub fn summary(self, res: &Matrix) -> Result<String, String> {
+        let pairs = res.nvals()?;
+        Ok(match self {
+            Semiring::Boolean => format!("{} pairs", pairs),
+            Semiring::Counting(_) => {
+                let paths = unsafe { res.reduce_u64(grb::GrB_PLUS_MONOID_UINT64)? };
+                format!("{} pairs, {} paths", pairs, paths)
+            }
+            Semiring::Tropical => {
+                let longest = unsafe { res.reduce_u64(grb::GrB_MAX_MONOID_UINT64)? };
+                format!("{} pairs, longest shortest path {}", pairs, longest)
+            }
+        })
+    }
+}
+
+/// Whether the graph of the entries of `step` has a cycle, i.e. the boolean
+/// closure of `step+` has an entry on its diagonal.
+fn has_cycle(step: &Matrix, cancel: &Cancel) -> Result<bool, EvalError> {
+    let size = step.nrows()?;
+    let plus = step.mxm(&*Semiring::Boolean.star(step, cancel)?)?;
+    let identity = OwnedMatrix::identity(size)?;
+    Ok(identity.without(&plus)?.nvals()? < size)
+}
+
+/// Evaluate the plan over the semiring returning the matrix of path values
+/// for every matching pair.
+pub fn eval_semiring(
+    graph: &Graph,
+    expr: RecExpr<Plan>,
+    semiring: Semiring,
+    cancel: &Cancel,
+) -> Result<OwnedMatrix, EvalError> {
+    let expr = push_transposes(&expr);
+    // Nodes come after their children, so shared subplans are evaluated once.
+    let mut values = vec![];
+    for id in 0..expr.as_ref().len() {
+        let value = semiring.node(graph, &expr, &values, id, cancel)?;
+        values.push(value);
+    }
+    Ok(values.pop().expect("plans are not empty"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::init_test;
+    use crate::plan::parse_plan;
+    use std::collections::HashMap;
+
+    fn summarize(graph: &Graph, plan: &str, semiring: Semiring) -> Result<String, EvalError> {
+        let plan = graph.resolve(&parse_plan(plan).unwrap())?;
+        let res = eval_semiring(graph, plan, semiring, &Cancel::default())?;
+        Ok(semiring.summary(&res)?)
+    }
+
+    #[test]
+    fn test_eval_semiring() {
+        init_test();
+        let verts = (0..3).map(|idx| (idx.to_string(), idx)).collect();
+        let edges = HashMap::from([
+            ("a".to_string(), vec![(0, 1), (1, 2), (0, 2)]),
+            ("c".to_string(), vec![(0, 1), (1, 0)]),
+        ]);
+        let graph = Graph::new(verts, edges).unwrap();
+        let counting = Semiring::Counting(None);
+        assert_eq!(
+            summarize(&graph, "(* <a>)", counting),
+            Ok("6 pairs, 7 paths".to_string())
+        );
+        assert_eq!(
+            summarize(&graph, "(/ <a> <a>)", counting),
+            Ok("1 pairs, 1 paths".to_string())
+        );
+        // Both alternatives match every path.
+        assert_eq!(
+            summarize(&graph, "(| <a> <a>)", counting),
+            Ok("3 pairs, 6 paths".to_string())
+        );
+        assert_eq!(
+            summarize(&graph, "(* <a>)", Semiring::Tropical),
+            Ok("6 pairs, longest shortest path 1".to_string())
+        );
+        assert_eq!(
+            summarize(&graph, "(/ <a> (* <c>))", counting),
+            Err(EvalError::Failed(
+                "closure over a cycle has infinitely many paths, bound their length".to_string()
+            ))
+        );
+        // Paths 0, 0-1, 0-1-0 and 0-1-0-1 from 0, the same from 1 and the
+        // empty path of 2.
+        assert_eq!(
+            summarize(&graph, "(* <c>)", Semiring::Counting(Some(3))),
+            Ok("5 pairs, 9 paths".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_semiring() {
+        assert_eq!("boolean".parse(), Ok(Semiring::Boolean));
+        assert_eq!("counting".parse(), Ok(Semiring::Counting(None)));
+        assert_eq!("counting:3".parse(), Ok(Semiring::Counting(Some(3))));
+        assert_eq!("tropical".parse(), Ok(Semiring::Tropical));
+        assert_eq!(
+            "counting:x".parse::<Semiring>(),
+            Err("invalid path length bound: x".to_string())
+        );
+        assert_eq!(
+            "max-plus".parse::<Semiring>(),
+            Err("unknown semiring: max-plus".to_string())
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    graph::Graph,
+    grb,
+    stats::{self, LabelStats, Stats},
+};
+
+/// Snapshot files start with the magic, the format version, padding and the
+/// checksum of the rest of the file.
+///
+/// The rest holds little-endian `u64`s only: the number of vertices followed
+/// by the matrix index and name of each, then the number of labels followed by the
+/// name, the dimensions, the number of entries and the compressed rows (see
+/// [`grb::Matrix::import_csr`]) of each. The statistics of the labels follow:
+/// the number of labels followed by the name, the number of entries, sources
+/// and targets, the maximum out- and in-degree and the number of self loops of
+/// each, then the number of composable pairs followed by the names of the
+/// labels and the number of shared vertices of each. Names are written as
+/// their length and their bytes padded with zeros to a multiple of 8, so the
+/// arrays stay aligned.
+const MAGIC: &[u8; 8] = b"RPQSNAP\0";
+const VERSION: u32 = 3;
+const HEADER_LEN: usize = 24;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Continue the FNV-1a hash with the bytes.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+fn padding(len: usize) -> usize {
+    (8 - len % 8) % 8
+}
+
+/// Writer hashing everything written.
+struct Checksummed<W: Write> {
+    inner: W,
+    hash: u64,
+}
+
+impl<W: Write> Checksummed<W> {
+    fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.hash = fnv1a(self.hash, bytes);
+        self.inner.write_all(bytes)
+    }
+
+    fn u64(&mut self, value: u64) -> io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u64s(&mut self, values: &[u64]) -> io::Result<()> {
+        values.iter().try_for_each(|&value| self.u64(value))
+    }
+
+    fn str(&mut self, s: &str) -> io::Result<()> {
+        self.u64(s.len() as u64)?;
+        self.bytes(s.as_bytes())?;
+        self.bytes(&[0; 8][..padding(s.len())])
+    }
+}
+
+/// Reader of the snapshot body.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.data.len() {
+            return Err("truncated snapshot".to_string());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        let len = self.u64()? as usize;
+        match len.checked_mul(8) {
+            Some(bytes) if bytes <= self.data.len() => Ok(len),
+            _ => Err("truncated snapshot".to_string()),
+        }
+    }
+
+    fn u64s(&mut self, len: usize) -> Result<Vec<u64>, String> {
+        let bytes = self.bytes(len.checked_mul(8).ok_or("truncated snapshot")?)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn str(&mut self) -> Result<&'a str, String> {
+        let len = self.u64()? as usize;
+        let bytes = self.bytes(len)?;
+        self.bytes(padding(len))?;
+        std::str::from_utf8(bytes).map_err(|_| "invalid name".to_string())
+    }
+}
+
+/// Write the graph into a snapshot file, with the statistics of its labels
+/// computed when the graph has none.
+pub fn save_snapshot(graph: &Graph, path: &Path) -> io::Result<()> {
+    let other = |msg| io::Error::new(io::ErrorKind::Other, msg);
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&[0; 4])?;
+    file.write_all(&0u64.to_le_bytes())?;
+
+    let mut body = Checksummed {
+        inner: &mut file,
+        hash: FNV_OFFSET,
+    };
+    body.u64(graph.nverts() as u64)?;
+    for idx in 0..graph.dim() {
+        body.u64(idx)?;
+        body.str(graph.vertex_name(idx))?;
+    }
+    let labels = graph.labels();
+    body.u64(labels.len() as u64)?;
+    for name in &labels {
+        let mat = graph.matrix(name).map_err(other)?;
+        let (rows, cols) = mat.export_csr().map_err(other)?;
+        body.str(name)?;
+        body.u64(mat.nrows().map_err(other)?)?;
+        body.u64(mat.ncols().map_err(other)?)?;
+        body.u64(cols.len() as u64)?;
+        body.u64s(&rows)?;
+        body.u64s(&cols)?;
+    }
+    let stats = match graph.stats() {
+        Some(stats) => stats,
+        None => Arc::new(stats::compute(graph, None).map_err(other)?),
+    };
+    body.u64(stats.labels.len() as u64)?;
+    for (name, label) in &stats.labels {
+        body.str(name)?;
+        body.u64s(&[
+            label.nvals,
+            label.sources,
+            label.targets,
+            label.max_out,
+            label.max_in,
+            label.self_loops,
+        ])?;
+    }
+    body.u64(stats.composability.len() as u64)?;
+    for ((lhs, rhs), &common) in &stats.composability {
+        body.str(lhs)?;
+        body.str(rhs)?;
+        body.u64(common)?;
+    }
+    let hash = body.hash;
+
+    file.seek(SeekFrom::Start(16))?;
+    file.write_all(&hash.to_le_bytes())?;
+    file.flush()
+}
+
+fn parse_snapshot(data: &[u8]) -> Result<Graph, String> {
+    if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+        return Err("not a graph snapshot".to_string());
+    }
+    let version = u32::from_le_bytes(data[8..12].try_into().unwrap());
+    if version != VERSION {
+        return Err(format!("unsupported snapshot version {}", version));
+    }
+    let hash = u64::from_le_bytes(data[16..24].try_into().unwrap());
+    if fnv1a(FNV_OFFSET, &data[HEADER_LEN..]) != hash {
+        return Err("checksum mismatch".to_string());
+    }
+
+    let mut body = Reader {
+        data: &data[HEADER_LEN..],
+    };
+    let nverts = body.len()?;
+    let mut verts = HashMap::with_capacity(nverts);
+    for _ in 0..nverts {
+        let idx = body.u64()? as usize;
+        verts.insert(body.str()?.to_string(), idx);
+    }
+    let nlabels = body.len()?;
+    let mut mats = HashMap::with_capacity(nlabels);
+    for _ in 0..nlabels {
+        let name = body.str()?;
+        let nrows = body.u64()?;
+        let ncols = body.u64()?;
+        let nvals = body.u64()? as usize;
+        let nptrs = (nrows as usize)
+            .checked_add(1)
+            .ok_or("truncated snapshot")?;
+        let rows = body.u64s(nptrs)?;
+        let cols = body.u64s(nvals)?;
+        let mat = grb::Matrix::import_csr(nrows, ncols, &rows, &cols)?;
+        mats.insert(name.to_string(), mat);
+    }
+
+    let mut stats = Stats::default();
+    for _ in 0..body.len()? {
+        let name = body.str()?.to_string();
+        let label = LabelStats {
+            nvals: body.u64()?,
+            sources: body.u64()?,
+            targets: body.u64()?,
+            max_out: body.u64()?,
+            max_in: body.u64()?,
+            self_loops: body.u64()?,
+        };
+        stats.labels.insert(name, label);
+    }
+    for _ in 0..body.len()? {
+        let pair = (body.str()?.to_string(), body.str()?.to_string());
+        stats.composability.insert(pair, body.u64()?);
+    }
+    let mut graph = Graph::from_mats(verts, mats)?;
+    graph.set_stats(Some(Arc::new(stats)));
+    Ok(graph)
+}
+
+/// Load a graph from a snapshot written by [`save_snapshot`], after checking
+/// its version and checksum.
+pub fn load_snapshot(path: &Path) -> io::Result<Graph> {
+    let data = std::fs::read(path)?;
+    parse_snapshot(&data).map_err(|msg| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), msg),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_vertices() {
+        let path = std::env::temp_dir().join(format!("rpq-{}.snap", std::process::id()));
+        let verts: HashMap<String, usize> = [("a".to_string(), 0), ("long name".to_string(), 1)]
+            .into_iter()
+            .collect();
+        let graph = Graph::from_mats(verts.clone(), HashMap::new()).unwrap();
+        save_snapshot(&graph, &path).unwrap();
+
+        let mut data = std::fs::read(&path).unwrap();
+        assert_eq!(load_snapshot(&path).unwrap().verts, verts);
+        assert_eq!(data.len() % 8, 0);
+
+        *data.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            parse_snapshot(&data).err(),
+            Some("checksum mismatch".to_string())
+        );
+        data[8] = 1;
+        assert_eq!(
+            parse_snapshot(&data).err(),
+            Some("unsupported snapshot version 1".to_string())
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_stats() {
+        crate::eval::init_test();
+        let path = std::env::temp_dir().join(format!("rpq-{}-stats.snap", std::process::id()));
+        let verts = HashMap::from([("a".to_string(), 0), ("b".to_string(), 1)]);
+        let edges = HashMap::from([("x".to_string(), vec![(0, 1), (1, 1)])]);
+        let graph = Graph::new(verts, edges).unwrap();
+        save_snapshot(&graph, &path).unwrap();
+        let loaded = load_snapshot(&path).unwrap();
+        assert_eq!(
+            loaded.stats().as_deref(),
+            Some(&stats::compute(&graph, None).unwrap())
+        );
+        assert_eq!(
+            loaded.matrix("x").unwrap().pairs(),
+            Ok(vec![(0, 1), (1, 1)])
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // A label claiming u64::MAX rows.
+        let mut body = Checksummed {
+            inner: vec![],
+            hash: FNV_OFFSET,
+        };
+        body.u64s(&[0, 1]).unwrap();
+        body.str("x").unwrap();
+        body.u64s(&[u64::MAX, 0, 0]).unwrap();
+        let mut data = MAGIC.to_vec();
+        data.extend(VERSION.to_le_bytes());
+        data.extend([0; 4]);
+        data.extend(body.hash.to_le_bytes());
+        data.extend(body.inner);
+        assert_eq!(
+            parse_snapshot(&data).err(),
+            Some("truncated snapshot".to_string())
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use egg::{Id, Language, RecExpr};
+
+use crate::eval::{selector, Cancel, EvalError};
+use crate::graph::Graph;
+use crate::grb::{Index, Matrix, OwnedMatrix};
+use crate::plan::{push_transposes, Plan};
+use crate::update::Update;
+
+/// Answer pairs gained and lost by an update.
+pub struct Changes {
+    pub added: Vec<(Index, Index)>,
+    pub removed: Vec<(Index, Index)>,
+}
+
+/// A query whose result is kept up to date while the graph changes.
+///
+/// The matrix of every plan node is kept, including the closures of `*`, so
+/// an inserted edge only propagates the pairs it adds up the plan. Deleted
+/// edges recompute the nodes depending on their label.
+pub struct StandingQuery {
+    pub name: String,
+    /// Plan using only labels, transposed labels, vertices, `/`, `|` and `*`.
+    expr: RecExpr<Plan>,
+    values: Vec<OwnedMatrix>,
+    dim: Index,
+}
+
+/// Rewrite `*r`, `l*` and `vxm` with `/` and `*`, so every closure is a node
+/// of its own.
+fn normalize(expr: &RecExpr<Plan>) -> RecExpr<Plan> {
+    let expr = push_transposes(expr);
+    let mut res = RecExpr::default();
+    let mut ids = vec![];
+    for node in expr.as_ref() {
+        let id = |child: Id| ids[usize::from(child)];
+        let node = match *node {
+            Plan::LStar([a, b]) => {
+                let star = res.add(Plan::Star([id(a)]));
+                Plan::Seq([star, id(b)])
+            }
+            Plan::RStar([a, b]) => {
+                let star = res.add(Plan::Star([id(b)]));
+                Plan::Seq([id(a), star])
+            }
+            Plan::Vxm([a, b]) => Plan::Seq([id(a), id(b)]),
+            ref node => node.clone().map_children(id),
+        };
+        ids.push(res.add(node));
+    }
+    res
+}
+
+/// Keep `delta` if it has any entries.
+fn nonempty(delta: OwnedMatrix) -> Result<Option<OwnedMatrix>, String> {
+    Ok(Some(delta).filter(|delta| delta.nvals().map_or(true, |nvals| nvals > 0)))
+}
+
+/// Add `step` to `res` until nothing changes, where `step` computes new pairs
+/// from the current `res`.
+fn fixpoint(
+    res: &mut OwnedMatrix,
+    cancel: &Cancel,
+    step: impl Fn(&Matrix) -> Result<OwnedMatrix, String>,
+) -> Result<(), EvalError> {
+    loop {
+        cancel.check()?;
+        cancel.check_nvals(res.nvals()?)?;
+        let before = res.nvals()?;
+        let next = step(res)?;
+        res.union(&next)?;
+        if res.nvals()? == before {
+            return Ok(());
+        }
+    }
+}
+
+impl StandingQuery {
+    /// Evaluate the plan of the query and keep the results of all its nodes.
+    pub fn new(
+        graph: &Graph,
+        name: String,
+        expr: &RecExpr<Plan>,
+        cancel: &Cancel,
+    ) -> Result<StandingQuery, EvalError> {
+        let mut query = StandingQuery {
+            name,
+            expr: normalize(expr),
+            values: vec![],
+            dim: graph.dim(),
+        };
+        query.recompute(graph, |_| true, cancel)?;
+        Ok(query)
+    }
+
+    /// Get the pairs of vertices answering the query.
+    pub fn result(&self) -> &Matrix {
+        self.values.last().unwrap()
+    }
+
+    /// Evaluate a node from the results of its children.
+    fn eval_node(
+        &self,
+        graph: &Graph,
+        id: usize,
+        cancel: &Cancel,
+    ) -> Result<OwnedMatrix, EvalError> {
+        cancel.check()?;
+        let value = |child: Id| &self.values[usize::from(child)];
+        Ok(match &self.expr.as_ref()[id] {
+            Plan::Label(meta) => graph.matrix(&meta.name)?.dup()?,
+            &Plan::Transpose([label]) => OwnedMatrix(value(label).transpose()?),
+            Plan::Vertex(meta) => OwnedMatrix(selector(graph, &meta.name)?),
+            &Plan::Seq([a, b]) => value(a).mxm(value(b))?,
+            &Plan::Alt([a, b]) => {
+                let mut res = value(a).dup()?;
+                res.union(value(b))?;
+                res
+            }
+            &Plan::Star([a]) => {
+                let mut res = OwnedMatrix::identity(self.dim)?;
+                fixpoint(&mut res, cancel, |res| res.mxm(value(a)))?;
+                res
+            }
+            node => return Err(format!("unexpected plan node: {}", node).into()),
+        })
+    }
+
+    /// Evaluate the selected nodes again, in order so children come first.
+    fn recompute(
+        &mut self,
+        graph: &Graph,
+        selected: impl Fn(usize) -> bool,
+        cancel: &Cancel,
+    ) -> Result<(), EvalError> {
+        for id in 0..self.expr.as_ref().len() {
+            if id < self.values.len() && !selected(id) {
+                continue;
+            }
+            let value = self.eval_node(graph, id, cancel)?;
+            match self.values.get_mut(id) {
+                Some(old) => *old = value,
+                None => self.values.push(value),
+            }
+        }
+        Ok(())
+    }
+
+    /// Nodes depending on the label.
+    fn depends_on(&self, label: &str) -> Vec<bool> {
+        let mut res = vec![false; self.expr.as_ref().len()];
+        for (id, node) in self.expr.as_ref().iter().enumerate() {
+            res[id] = match node {
+                Plan::Label(meta) => meta.name == label,
+                node => node.children().iter().any(|&child| res[usize::from(child)]),
+            };
+        }
+        res
+    }
+
+    /// Propagate the pairs added by the inserted edge from the labels to the
+    /// root, adding them to the kept results.
+    fn insert(
+        &mut self,
+        label: &str,
+        edge: (Index, Index),
+        cancel: &Cancel,
+    ) -> Result<(), EvalError> {
+        let mut deltas: Vec<Option<OwnedMatrix>> = vec![];
+        for id in 0..self.expr.as_ref().len() {
+            cancel.check()?;
+            let delta = |child: Id| deltas[usize::from(child)].as_ref();
+            let value = |child: Id| &self.values[usize::from(child)];
+            let old = &self.values[id];
+            let added = match &self.expr.as_ref()[id] {
+                Plan::Label(meta) if meta.name == label => {
+                    Some(OwnedMatrix(Matrix::build(self.dim, &[edge])?))
+                }
+                &Plan::Transpose([a]) => match delta(a) {
+                    Some(delta) => Some(OwnedMatrix(delta.transpose()?)),
+                    None => None,
+                },
+                // (A + dA)(B + dB) adds dA B' and A' dB, with the updated A'
+                // and B'.
+                &Plan::Seq([a, b]) => match (delta(a), delta(b)) {
+                    (None, None) => None,
+                    (delta_a, delta_b) => {
+                        let mut res = OwnedMatrix::new(self.dim, self.dim)?;
+                        if let Some(delta_a) = delta_a {
+                            res.union(&*delta_a.mxm(value(b))?)?;
+                        }
+                        if let Some(delta_b) = delta_b {
+                            res.union(&*value(a).mxm(delta_b)?)?;
+                        }
+                        Some(res)
+                    }
+                },
+                &Plan::Alt([a, b]) => match (delta(a), delta(b)) {
+                    (None, None) => None,
+                    (delta_a, delta_b) => {
+                        let mut res = OwnedMatrix::new(self.dim, self.dim)?;
+                        delta_a.map_or(Ok(()), |delta| res.union(delta))?;
+                        delta_b.map_or(Ok(()), |delta| res.union(delta))?;
+                        Some(res)
+                    }
+                },
+                // (A + dA)* = S (dA S)* for S = A*, which adds S (dA S)+.
+                &Plan::Star([a]) => match delta(a) {
+                    Some(delta) => {
+                        let step = delta.mxm(old)?;
+                        let mut steps = step.dup()?;
+                        fixpoint(&mut steps, cancel, |steps| steps.mxm(&step))?;
+                        Some(old.mxm(&steps)?)
+                    }
+                    None => None,
+                },
+                _ => None,
+            };
+            let added = match added {
+                Some(added) => nonempty(added.without(old)?)?,
+                None => None,
+            };
+            if let Some(added) = &added {
+                self.values[id].union(added)?;
+            }
+            deltas.push(added);
+        }
+        Ok(())
+    }
+
+    /// Bring the results up to date with an update applied to the graph and
+    /// get the changed answers.
+    pub fn update(
+        &mut self,
+        graph: &Graph,
+        update: &Update,
+        cancel: &Cancel,
+    ) -> Result<Changes, EvalError> {
+        let old = self.result().dup()?;
+        // Compare with the old answers grown to the new vertices.
+        old.resize(graph.dim(), graph.dim())?;
+        match update {
+            // New vertices grow every matrix and the closures.
+            _ if graph.dim() != self.dim => {
+                self.dim = graph.dim();
+                self.recompute(graph, |_| true, cancel)?;
+            }
+            Update::Insert(src, label, dest) => {
+                let edge = (graph.verts[src] as Index, graph.verts[dest] as Index);
+                self.insert(label, edge, cancel)?;
+            }
+            Update::Delete(_, label, _) => {
+                let affected = self.depends_on(label);
+                self.recompute(graph, |id| affected[id], cancel)?;
+            }
+        }
+        Ok(Changes {
+            added: self.result().without(&old)?.pairs()?,
+            removed: old.without(self.result())?.pairs()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::plan::{parse_plan, write_plan};
+
+    #[test]
+    fn test_normalize() {
+        let expr = parse_plan("(*r <a> (vxm {v} (l* (t <b>) <c>)))").unwrap();
+        assert_eq!(
+            write_plan(&normalize(&expr)),
+            "(/ (* <a>) (/ {v} (/ (t <b>) (* <c>))))"
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        crate::eval::init_test();
+        let verts = HashMap::from([
+            ("a".to_string(), 0),
+            ("b".to_string(), 1),
+            ("c".to_string(), 2),
+        ]);
+        let edges = HashMap::from([
+            ("x".to_string(), vec![(0, 1), (1, 2)]),
+            ("y".to_string(), vec![(2, 0)]),
+        ]);
+        let mut graph = Graph::new(verts, edges).unwrap();
+        let cancel = Cancel::default();
+        let plans = [
+            "(/ <x> (* <y>))",
+            "(| (t <y>) (/ (* <x>) <y>))",
+            "(*r (| <x> <y>) <x>)",
+        ];
+        let mut queries: Vec<StandingQuery> = plans
+            .iter()
+            .map(|plan| {
+                let expr = parse_plan(plan).unwrap();
+                StandingQuery::new(&graph, plan.to_string(), &expr, &cancel).unwrap()
+            })
+            .collect();
+        let update = |op: char, src: &str, label: &str, dest: &str| {
+            let (src, label, dest) = (src.to_string(), label.to_string(), dest.to_string());
+            match op {
+                '+' => Update::Insert(src, label, dest),
+                _ => Update::Delete(src, label, dest),
+            }
+        };
+        let updates = [
+            update('+', "c", "y", "b"),
+            update('+', "c", "x", "d"),
+            update('+', "d", "y", "d"),
+            update('-', "a", "x", "b"),
+            update('+', "d", "x", "a"),
+            update('-', "c", "y", "a"),
+        ];
+        let sorted = |mat: &Matrix| {
+            let mut pairs = mat.pairs().unwrap();
+            pairs.sort();
+            pairs
+        };
+        for update in &updates {
+            assert!(update.apply(&mut graph).unwrap());
+            for query in &mut queries {
+                let old = sorted(query.result());
+                let changes = query.update(&graph, update, &cancel).unwrap();
+                let expr = parse_plan(&query.name).unwrap();
+                let fresh = StandingQuery::new(&graph, query.name.clone(), &expr, &cancel).unwrap();
+                let expected = sorted(fresh.result());
+                assert_eq!(
+                    sorted(query.result()),
+                    expected,
+                    "{} after {}",
+                    query.name,
+                    update
+                );
+                let mut added: Vec<_> = expected
+                    .iter()
+                    .filter(|pair| !old.contains(pair))
+                    .copied()
+                    .collect();
+                added.sort();
+                let mut got = changes.added;
+                got.sort();
+                assert_eq!(got, added, "{} after {}", query.name, update);
+                let mut removed: Vec<_> = old
+                    .iter()
+                    .filter(|pair| !expected.contains(pair))
+                    .copied()
+                    .collect();
+                removed.sort();
+                let mut got = changes.removed;
+                got.sort();
+                assert_eq!(got, removed, "{} after {}", query.name, update);
+            }
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use crate::graph::Graph;
+
+/// Degree statistics of a label.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LabelStats {
+    pub nvals: u64,
+    /// Vertices with an outgoing edge.
+    pub sources: u64,
+    /// Vertices with an incoming edge.
+    pub targets: u64,
+    pub max_out: u64,
+    pub max_in: u64,
+    pub self_loops: u64,
+}
+
+impl LabelStats {
+    /// Average out-degree of the sources.
+    pub fn avg_out(&self) -> f64 {
+        self.nvals as f64 / self.sources.max(1) as f64
+    }
+
+    /// Average in-degree of the targets.
+    pub fn avg_in(&self) -> f64 {
+        self.nvals as f64 / self.targets.max(1) as f64
+    }
+}
+
+/// Statistics of all labels of a graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub labels: BTreeMap<String, LabelStats>,
+    /// Number of targets of the first label which are sources of the second,
+    /// for pairs sharing any vertex.
+    pub composability: BTreeMap<(String, String), u64>,
+    /// Latest modification time of the files of the graph, in nanoseconds
+    /// since the epoch, to tell whether kept statistics are still current.
+    pub modified: u64,
+}
+
+/// Set of vertices.
+struct Bitset(Vec<u64>);
+
+impl Bitset {
+    fn new(size: usize) -> Bitset {
+        Bitset(vec![0; (size + 63) / 64])
+    }
+
+    fn insert(&mut self, idx: usize) {
+        self.0[idx / 64] |= 1 << (idx % 64);
+    }
+
+    fn len(&self) -> u64 {
+        self.0.iter().map(|word| word.count_ones() as u64).sum()
+    }
+
+    fn common(&self, other: &Bitset) -> u64 {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(lhs, rhs)| (lhs & rhs).count_ones() as u64)
+            .sum()
+    }
+}
+
+/// Compute the statistics from the compressed rows of every label, keeping at
+/// most `keep` label matrices read from files in memory meanwhile.
+pub fn compute(graph: &Graph, keep: Option<usize>) -> Result<Stats, String> {
+    let size = graph.dim() as usize;
+    let mut stats = Stats::default();
+    let mut ends = vec![];
+    for name in graph.labels() {
+        let (rows, cols) = graph.matrix(&name)?.export_csr()?;
+        if let Some(keep) = keep {
+            graph.evict(keep);
+        }
+        let mut label = LabelStats {
+            nvals: cols.len() as u64,
+            ..LabelStats::default()
+        };
+        let mut sources = Bitset::new(size);
+        let mut targets = Bitset::new(size);
+        let mut in_degrees = vec![0; size];
+        for (row, bounds) in rows.windows(2).enumerate() {
+            let row_cols = &cols[bounds[0] as usize..bounds[1] as usize];
+            if !row_cols.is_empty() {
+                sources.insert(row);
+            }
+            label.max_out = label.max_out.max(row_cols.len() as u64);
+            for &col in row_cols {
+                targets.insert(col as usize);
+                in_degrees[col as usize] += 1;
+                label.self_loops += (col as usize == row) as u64;
+            }
+        }
+        label.sources = sources.len();
+        label.targets = targets.len();
+        label.max_in = in_degrees.into_iter().max().unwrap_or(0);
+        stats.labels.insert(name.clone(), label);
+        ends.push((name, sources, targets));
+    }
+    for (lhs, _, targets) in &ends {
+        for (rhs, sources, _) in &ends {
+            let common = targets.common(sources);
+            if common > 0 {
+                stats
+                    .composability
+                    .insert((lhs.clone(), rhs.clone()), common);
+            }
+        }
+    }
+    Ok(stats)
+}
+
+/// File the statistics of a graph are kept in: `stats.tsv` inside a dataset
+/// directory, or next to a graph file with `.stats` appended.
+pub fn stats_file(graph_path: &Path) -> PathBuf {
+    if graph_path.is_dir() {
+        graph_path.join("stats.tsv")
+    } else {
+        let mut file = graph_path.as_os_str().to_owned();
+        file.push(".stats");
+        file.into()
+    }
+}
+
+/// Latest modification time of the files of the graph besides its statistics,
+/// in nanoseconds since the epoch.
+pub fn modified(graph_path: &Path) -> io::Result<u64> {
+    let stamp = |meta: std::fs::Metadata| -> io::Result<u64> {
+        let since = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        Ok(since.as_nanos() as u64)
+    };
+    if !graph_path.is_dir() {
+        return stamp(std::fs::metadata(graph_path)?);
+    }
+    let stats = stats_file(graph_path);
+    let mut latest = 0;
+    for entry in std::fs::read_dir(graph_path)? {
+        let entry = entry?;
+        if entry.path() != stats {
+            latest = latest.max(stamp(entry.metadata()?)?);
+        }
+    }
+    Ok(latest)
+}
+
+/// Escape backslashes, tabs and line breaks of a label name.
+fn escape(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '\t' => res.push_str("\\t"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Undo [`escape`].
+fn unescape(name: &str) -> Result<String, String> {
+    let mut res = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        res.push(match chars.next() {
+            Some('\\') => '\\',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            _ => return Err(format!("invalid escape in name: {}", name)),
+        });
+    }
+    Ok(res)
+}
+
+/// Write the statistics as tab separated `label`, `compose` and `modified`
+/// lines. Names are escaped with backslashes.
+pub fn save(stats: &Stats, file: &Path) -> io::Result<()> {
+    let mut file = BufWriter::new(std::fs::File::create(file)?);
+    writeln!(
+        file,
+        "# label\tname\tnvals\tsources\ttargets\tmax_out\tmax_in\tself_loops"
+    )?;
+    for (name, label) in &stats.labels {
+        writeln!(
+            file,
+            "label\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            escape(name),
+            label.nvals,
+            label.sources,
+            label.targets,
+            label.max_out,
+            label.max_in,
+            label.self_loops
+        )?;
+    }
+    writeln!(file, "# compose\tlhs\trhs\tcommon")?;
+    for ((lhs, rhs), common) in &stats.composability {
+        writeln!(
+            file,
+            "compose\t{}\t{}\t{}",
+            escape(lhs),
+            escape(rhs),
+            common
+        )?;
+    }
+    writeln!(file, "# modified\tnanoseconds")?;
+    writeln!(file, "modified\t{}", stats.modified)?;
+    file.flush()
+}
+
+fn parse_line(stats: &mut Stats, line: &str) -> Result<(), String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let num = |field: &str| {
+        field
+            .parse::<u64>()
+            .map_err(|_| format!("invalid number: {}", field))
+    };
+    match fields[..] {
+        ["label", name, nvals, sources, targets, max_out, max_in, self_loops] => {
+            let label = LabelStats {
+                nvals: num(nvals)?,
+                sources: num(sources)?,
+                targets: num(targets)?,
+                max_out: num(max_out)?,
+                max_in: num(max_in)?,
+                self_loops: num(self_loops)?,
+            };
+            stats.labels.insert(unescape(name)?, label);
+        }
+        ["compose", lhs, rhs, common] => {
+            let pair = (unescape(lhs)?, unescape(rhs)?);
+            stats.composability.insert(pair, num(common)?);
+        }
+        ["modified", modified] => stats.modified = num(modified)?,
+        _ => return Err("expected a label, compose or modified line".to_string()),
+    }
+    Ok(())
+}
+
+/// Read statistics written by [`save`].
+pub fn load(file: &Path) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+    for (num, line) in std::fs::read_to_string(file)?.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        parse_line(&mut stats, line).map_err(|msg| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", file.display(), num + 1, msg),
+            )
+        })?;
+    }
+    Ok(stats)
+}
+
+/// Read the statistics kept next to the graph if its files did not change
+/// since they were computed.
+pub fn load_current(graph_path: &Path) -> Option<Stats> {
+    let stats = load(&stats_file(graph_path)).ok()?;
+    Some(stats).filter(|stats| modified(graph_path).ok() == Some(stats.modified))
+}
+
+/// Get the statistics the graph was loaded with, or compute them and keep
+/// them next to the graph unless it was `changed` since it was read.
+pub fn load_or_compute(
+    graph: &Graph,
+    graph_path: &Path,
+    keep: Option<usize>,
+    changed: bool,
+) -> Result<Arc<Stats>, String> {
+    if let Some(stats) = graph.stats() {
+        return Ok(stats);
+    }
+    let mut stats = compute(graph, keep)?;
+    if !changed {
+        let file = stats_file(graph_path);
+        let saved = modified(graph_path).and_then(|modified| {
+            stats.modified = modified;
+            save(&stats, &file)
+        });
+        if let Err(err) = saved {
+            eprintln!("unable to save statistics to {}: {}", file.display(), err);
+        }
+    }
+    Ok(Arc::new(stats))
+}
+
+/// Print a table of the label statistics followed by the composable pairs.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>10} {:>10} {:>10} {:>8} {:>8} {:>8} {:>8} {:>10}",
+            "label",
+            "nvals",
+            "sources",
+            "targets",
+            "max out",
+            "avg out",
+            "max in",
+            "avg in",
+            "self loops"
+        )?;
+        for (name, label) in &self.labels {
+            writeln!(
+                f,
+                "{:<24} {:>10} {:>10} {:>10} {:>8} {:>8.2} {:>8} {:>8.2} {:>10}",
+                name,
+                label.nvals,
+                label.sources,
+                label.targets,
+                label.max_out,
+                label.avg_out(),
+                label.max_in,
+                label.avg_in(),
+                label.self_loops
+            )?;
+        }
+        if !self.composability.is_empty() {
+            writeln!(
+                f,
+                "\nComposable labels (targets of the first which are sources of the second):"
+            )?;
+        }
+        for ((lhs, rhs), common) in &self.composability {
+            writeln!(f, "    {} / {}: {}", lhs, rhs, common)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let mut stats = Stats::default();
+        stats.labels.insert(
+            "knows".to_string(),
+            LabelStats {
+                nvals: 6,
+                sources: 3,
+                targets: 2,
+                max_out: 3,
+                max_in: 4,
+                self_loops: 1,
+            },
+        );
+        stats
+            .composability
+            .insert(("knows".to_string(), "knows".to_string()), 2);
+        let odd = "a\tb\\n\nc\r".to_string();
+        stats.labels.insert(odd.clone(), LabelStats::default());
+        stats.composability.insert((odd, "knows".to_string()), 1);
+        stats.modified = 1_700_000_000_000_000_000;
+        let file = std::env::temp_dir().join(format!("rpq-{}.stats", std::process::id()));
+        save(&stats, &file).unwrap();
+        assert_eq!(load(&file).unwrap(), stats);
+        assert_eq!(stats.labels["knows"].avg_out(), 2.0);
+        assert_eq!(stats.labels["knows"].avg_in(), 3.0);
+        std::fs::write(&file, "label\tknows\t1\n").unwrap();
+        assert!(load(&file)
+            .unwrap_err()
+            .to_string()
+            .ends_with(":1: expected a label, compose or modified line"));
+        std::fs::write(&file, "label\ta\\x\t0\t0\t0\t0\t0\t0\n").unwrap();
+        assert!(load(&file)
+            .unwrap_err()
+            .to_string()
+            .ends_with(":1: invalid escape in name: a\\x"));
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_load_current() {
+        let dir = std::env::temp_dir().join(format!("rpq-{}-stats", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("edges.txt"), "<a> 1\n").unwrap();
+        assert_eq!(load_current(&dir), None);
+        let stats = Stats {
+            modified: modified(&dir).unwrap(),
+            ..Stats::default()
+        };
+        save(&stats, &stats_file(&dir)).unwrap();
+        assert_eq!(load_current(&dir), Some(stats));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        std::fs::write(dir.join("edges.txt"), "<b> 1\n").unwrap();
+        assert_eq!(load_current(&dir), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+use std::{fmt, io, path::Path};
+
+use crate::graph::{parse_name, write_name, Graph};
+
+/// A change of an edge, given by the names of its source, label and
+/// destination.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Update {
+    Insert(String, String, String),
+    Delete(String, String, String),
+}
+
+/// Parse a `+ <src> <label> <dest>` insertion or a `- <src> <label> <dest>`
+/// deletion. Names containing `>` are quoted as in `vertices.txt`.
+fn parse_update(line: &str) -> Result<Update, String> {
+    let mut chars = line.chars();
+    let op = chars.next();
+    let rest = chars.as_str();
+    let (src, rest) = parse_name(rest.trim_start())?;
+    let (label, rest) = parse_name(rest.trim_start())?;
+    let (dest, rest) = parse_name(rest.trim_start())?;
+    if !rest.trim().is_empty() {
+        return Err(format!("unexpected text: {}", rest.trim()));
+    }
+    match op {
+        Some('+') => Ok(Update::Insert(src, label, dest)),
+        Some('-') => Ok(Update::Delete(src, label, dest)),
+        _ => Err(format!("expected + or - instead of: {}", line)),
+    }
+}
+
+/// Read a file of updates, one per line. Empty lines and lines starting with
+/// `#` are skipped.
+pub fn read_updates(file: &Path) -> io::Result<Vec<Update>> {
+    let mut updates = vec![];
+    for (num, line) in std::fs::read_to_string(file)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let update = parse_update(line).map_err(|msg| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", file.display(), num + 1, msg),
+            )
+        })?;
+        updates.push(update);
+    }
+    Ok(updates)
+}
+
+/// Print the update as it is written in update files.
+impl fmt::Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (op, src, label, dest) = match self {
+            Update::Insert(src, label, dest) => ('+', src, label, dest),
+            Update::Delete(src, label, dest) => ('-', src, label, dest),
+        };
+        write!(
+            f,
+            "{} {} {} {}",
+            op,
+            write_name(src),
+            write_name(label),
+            write_name(dest)
+        )
+    }
+}
+
+impl Update {
+    /// Change the graph. Returns whether it changed: inserted edges were
+    /// missing and deleted edges existed.
+    pub fn apply(&self, graph: &mut Graph) -> Result<bool, String> {
+        match self {
+            Update::Insert(src, label, dest) => graph.insert_edge(src, label, dest),
+            Update::Delete(src, label, dest) => graph.delete_edge(src, label, dest),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_update() {
+        assert_eq!(
+            parse_update("+ <a> <knows> <b c>"),
+            Ok(Update::Insert("a".into(), "knows".into(), "b c".into()))
+        );
+        assert_eq!(
+            parse_update("-<a><knows><b>"),
+            Ok(Update::Delete("a".into(), "knows".into(), "b".into()))
+        );
+        assert_eq!(
+            parse_update("* <a> <knows> <b>"),
+            Err("expected + or - instead of: * <a> <knows> <b>".to_string())
+        );
+        assert_eq!(
+            parse_update("+ <a> <knows>"),
+            Err("expected <name> or \"name\"".to_string())
+        );
+        assert_eq!(
+            parse_update(r#"+ "a>b" <knows> "\\c\"""#),
+            Ok(Update::Insert("a>b".into(), "knows".into(), "\\c\"".into()))
+        );
+        assert_eq!(
+            parse_update("+ <a> <knows> <b> <c>"),
+            Err("unexpected text: <c>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_display_update() {
+        let update = Update::Delete("a>b".into(), "knows".into(), "c d".into());
+        assert_eq!(update.to_string(), r#"- "a>b" <knows> <c d>"#);
+        assert_eq!(parse_update(&update.to_string()), Ok(update));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+use crate::eval::{Cancel, EvalError};
+use crate::graph::Graph;
+use crate::grb::{self, Index};
+use crate::query::Nfa;
+
+/// A path matching a pattern: the first vertex followed by the label and
+/// the vertex of every edge.
+pub struct Witness {
+    pub src: String,
+    pub steps: Vec<(String, String)>,
+}
+
+/// Print the path like `<1> -<a>-> <2> -<b>-> <3>`.
+impl fmt::Display for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.src)?;
+        self.steps
+            .iter()
+            .try_for_each(|(label, vert)| write!(f, " -<{}>-> <{}>", label, vert))
+    }
+}
+
+/// A vertex of the graph paired with a state of the automaton.
+type Node = (Index, usize);
+
+/// Neighbours of a vertex along a label.
+fn successors(graph: &Graph, label: &str, vert: Index) -> Result<Vec<Index>, String> {
+    let mat = graph.matrix(label)?;
+    let mut row = grb::Vector::new(mat.nrows()?)?;
+    row.set(vert)?;
+    row.vxm(&mat)?.indices()
+}
+
+/// Find a shortest path from `src` to `dest` whose labels are accepted by the
+/// automaton, searching the product of the graph and the automaton breadth
+/// first. Moves without a label are taken before the others, so paths are
+/// ordered by their number of edges.
+pub fn find_witness(
+    graph: &Graph,
+    nfa: &Nfa,
+    src: Index,
+    dest: Index,
+    cancel: &Cancel,
+) -> Result<Option<Witness>, EvalError> {
+    // Every reached (vertex, state) maps to its number of edges from the
+    // start, the label of the edge moved along, if any, and the pair it was
+    // reached from.
+    type Parent<'a> = (usize, Option<(Option<&'a str>, Node)>);
+    let mut parents: HashMap<Node, Parent> = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert((src, nfa.start), (0, None));
+    queue.push_back((0, (src, nfa.start)));
+    while let Some((dist, (vert, state))) = queue.pop_front() {
+        cancel.check()?;
+        if parents[&(vert, state)].0 < dist {
+            continue;
+        }
+        if (vert, state) == (dest, nfa.accept) {
+            break;
+        }
+        for (label, next_state) in &nfa.moves[state] {
+            let next = match label {
+                None => vec![(vert, *next_state)],
+                Some(label) => successors(graph, label, vert)?
+                    .into_iter()
+                    .map(|next_vert| (next_vert, *next_state))
+                    .collect(),
+            };
+            let next_dist = dist + label.is_some() as usize;
+            for next in next {
+                if parents
+                    .get(&next)
+                    .map_or(false, |&(known, _)| known <= next_dist)
+                {
+                    continue;
+                }
+                parents.insert(next, (next_dist, Some((label.as_deref(), (vert, state)))));
+                match label {
+                    None => queue.push_front((next_dist, next)),
+                    Some(_) => queue.push_back((next_dist, next)),
+                }
+            }
+        }
+    }
+    let mut node = (dest, nfa.accept);
+    if !parents.contains_key(&node) {
+        return Ok(None);
+    }
+    let mut steps = vec![];
+    while let (_, Some((label, prev))) = parents[&node] {
+        if let Some(label) = label {
+            steps.push((label.to_string(), graph.vertex_name(node.0).to_string()));
+        }
+        node = prev;
+    }
+    steps.reverse();
+    Ok(Some(Witness {
+        src: graph.vertex_name(src).to_string(),
+        steps,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::query::Pattern;
+
+    #[test]
+    fn test_fewest_edges() {
+        crate::eval::init_test();
+        let verts = HashMap::from([
+            ("a".to_string(), 0),
+            ("b".to_string(), 1),
+            ("c".to_string(), 2),
+        ]);
+        let edges = HashMap::from([
+            ("x".to_string(), vec![(0, 1)]),
+            ("y".to_string(), vec![(0, 2), (2, 1)]),
+        ]);
+        let graph = Graph::new(verts, edges).unwrap();
+        let uri = |label: &str| Box::new(Pattern::Uri(label.to_string()));
+        // <y>/<y> | (<x>*)*, where the single <x> edge takes more moves
+        // without a label.
+        let pattern = Pattern::Alt(
+            Box::new(Pattern::Seq(uri("y"), uri("y"))),
+            Box::new(Pattern::Star(Box::new(Pattern::Star(uri("x"))))),
+        );
+        let witness = find_witness(&graph, &pattern.nfa(), 0, 1, &Cancel::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(witness.to_string(), "<a> -<x>-> <b>");
+        assert!(
+            find_witness(&graph, &pattern.nfa(), 1, 0, &Cancel::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+}